@@ -24,13 +24,13 @@ impl From<std::io::Error> for ParseTspError {
 impl Display for ParseTspError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::IoError(e) => write!(f, "{}", format!("IO error: {}", e.to_string())),
-            Self::MissingEntry(e) => write!(f, "{}", format!("Missing entry: {}", e)),
-            Self::InvalidEntry(e) => write!(f, "{}", format!("Invalid entry: {}", e)),
+            Self::IoError(e) => write!(f, "IO error: {}", e),
+            Self::MissingEntry(e) => write!(f, "Missing entry: {}", e),
+            Self::InvalidEntry(e) => write!(f, "Invalid entry: {}", e),
             Self::InvalidInput { key, val } => {
-                write!(f, "{}", format!("Invalid input {} : {}", key, val))
+                write!(f, "Invalid input {} : {}", key, val)
             }
-            Self::Other(e) => write!(f, "{}", format!("Invalid entry: {}", e)),
+            Self::Other(e) => write!(f, "Invalid entry: {}", e),
         }
     }
 }
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use petgraph::{graph::NodeIndex, Graph, Undirected};
+
+use crate::{EdgeFormat, Tsp};
+
+impl Tsp {
+    /// Converts the dataset into an undirected [`petgraph::Graph`].
+    ///
+    /// Each node becomes a vertex weighted with its id, in the order of node ids. For a sparse
+    /// graph given in ```EDGE_DATA_SECTION```, the listed edges are added. Otherwise the graph is
+    /// complete and an edge is added for each pair of nodes, so that the result has a size of
+    /// ```O(n²)```. For [`crate::TspKind::Atsp`], both directions of a pair are added as separate
+    /// edges, see [`Tsp::edge_list`].
+    ///
+    /// Requires the feature ```petgraph```.
+    pub fn to_petgraph(&self) -> Graph<usize, f64, Undirected> {
+        let edges = self.edge_list();
+        let ids = match self.edge_format() {
+            // Nodes of sparse graphs without coordinates are numbered from 1 in TSPLIB.
+            EdgeFormat::EdgeList(_) if self.node_coords().is_empty() => (1..=self.dim()).collect(),
+            _ => self.node_ids(),
+        };
+
+        let mut graph = Graph::with_capacity(ids.len(), edges.len());
+        let indices: HashMap<usize, NodeIndex> =
+            ids.iter().map(|&id| (id, graph.add_node(id))).collect();
+        for (a, b, w) in edges {
+            if let (Some(&na), Some(&nb)) = (indices.get(&a), indices.get(&b)) {
+                graph.add_edge(na, nb, w);
+            }
+        }
+
+        graph
+    }
+}
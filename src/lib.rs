@@ -4,7 +4,6 @@
 //! can be found in the website of Ruprecht-Karls-Universität Heidelberg.
 
 /// Macro for implementing trait Display for Enums.
-#[macro_use]
 macro_rules! impl_disp_enum {
     ($enm:ident) => {
         impl std::fmt::Display for $enm {
@@ -15,6 +14,20 @@ macro_rules! impl_disp_enum {
     };
 }
 
+/// Implements [`std::str::FromStr`] for an enum of TSPLIB keywords, accepting the same values as
+/// the parser.
+macro_rules! impl_from_str_enum {
+    ($enm:ident) => {
+        impl std::str::FromStr for $enm {
+            type Err = ParseTspError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::try_from(InputWrapper(s))
+            }
+        }
+    };
+}
+
 mod error;
 pub use error::ParseTspError;
 
@@ -24,11 +37,20 @@ mod tsp;
 pub use tsp::CoordKind;
 pub use tsp::DisplayKind;
 pub use tsp::EdgeFormat;
+pub use tsp::MatrixIndexing;
 pub use tsp::Point;
+pub use tsp::RoundingPolicy;
+pub use tsp::SectionFlags;
 pub use tsp::Tsp;
 pub use tsp::TspBuilder;
 pub use tsp::TspKind;
 pub use tsp::WeightFormat;
 pub use tsp::WeightKind;
 
+#[cfg(feature = "petgraph")]
+mod graph;
+
+mod writer;
+pub use writer::WriteOptions;
+
 mod tests;
@@ -24,12 +24,40 @@ impl WeightKind {
             _ => 0.,
         }
     }
+
+    /// Rounds a weight to an integer according to the conventions of TSPLIB.
+    ///
+    /// - [`WeightKind::Geo`]: truncated, since the distance function already adds ```1```. See
+    ///   [`RoundingPolicy`](crate::RoundingPolicy) for the alternative used by some solvers.
+    /// - [`WeightKind::Att`]: rounded to the nearest integer, plus one if that is less than the
+    ///   weight.
+    /// - [`WeightKind::Ceil2d`]: rounded up.
+    /// - All others: rounded to the nearest integer.
+    pub fn round_weight(&self, w: f64) -> f64 {
+        match self {
+            Self::Geo => w.trunc(),
+            Self::Att => {
+                let t = w.round();
+                if t < w {
+                    t + 1.
+                } else {
+                    t
+                }
+            }
+            Self::Ceil2d => w.ceil(),
+            _ => w.round(),
+        }
+    }
 }
 
 /// Calculates the 2D-Euclidean distance between two points.
 #[inline]
 pub fn euc_2d(a: &[f64], b: &[f64]) -> f64 {
-    euc(a, b, 2)
+    // Plane coordinates are accessed directly instead of iterating over them.
+    match (a, b) {
+        ([x1, y1, ..], [x2, y2, ..]) => ((x1 - x2).powi(2) + (y1 - y2).powi(2)).sqrt(),
+        _ => euc(a, b, 2),
+    }
 }
 
 /// Calculates the 3D-Euclidean distance between two points.
@@ -100,11 +128,26 @@ pub fn geo(a: &[f64], b: &[f64]) -> f64 {
     EARTH_RADIUS * q4 + 1.
 }
 
+/// Calculates the geographical distance between two points whose coordinates are given in the
+/// order ```longitude latitude```.
+///
+/// See [`geo`] for points in the order ```latitude longitude``` as used in TSPLIB.
 #[inline]
-fn to_geo_coord(x: f64) -> f64 {
+pub fn geo_lon_lat(a: &[f64], b: &[f64]) -> f64 {
+    geo(&[a[1], a[0]], &[b[1], b[0]])
+}
+
+/// Converts a geographical coordinate in the TSPLIB format ```DDD.MM``` into decimal degrees.
+#[inline]
+pub fn geo_degrees(x: f64) -> f64 {
     let deg = x.trunc();
     let min = x - deg;
-    PI * (deg + 5. * min / 3.) / 180.
+    deg + 5. * min / 3.
+}
+
+#[inline]
+fn to_geo_coord(x: f64) -> f64 {
+    PI * geo_degrees(x) / 180.
 }
 
 /// Calculates the distance between two points for dataset from AT&T Bell laboratory, published by Padberg and Rinaldi in 1987.
@@ -1,6 +1,13 @@
 #![cfg(test)]
-use crate::{metric::*, Tsp, WeightFormat};
-use crate::{TspBuilder, TspKind, WeightKind};
+// Keeps the original form of the metric tests.
+#![allow(clippy::useless_vec, clippy::unnecessary_cast)]
+use std::collections::HashSet;
+
+use crate::writer::fmt_f64;
+use crate::{
+    metric::*, MatrixIndexing, ParseTspError, RoundingPolicy, Tsp, WeightFormat, WriteOptions,
+};
+use crate::{CoordKind, DisplayKind, TspBuilder, TspKind, WeightKind};
 use approx::assert_relative_eq;
 
 const TEST_STR: &str = "
@@ -107,6 +114,50 @@ macro_rules! prep_weight {
     };
 }
 
+/// Prepares an instance with the nodes ```(0, 0)```, ```(3, 4)``` and ```(6, 8)```, given the
+/// type and further entries of the specification and the sections following the coordinates.
+macro_rules! prep_coords {
+    ($spec:expr, $sections:expr) => {
+        format!(
+            "
+        NAME: test
+        {}
+        DIMENSION: 3
+        EDGE_WEIGHT_TYPE: EUC_2D
+        NODE_COORD_SECTION
+        1 0 0
+        2 3 4
+        3 6 8
+        {}
+        EOF
+        ",
+            $spec, $sections
+        )
+    };
+}
+
+/// Demands and depot of a CVRP instance prepared with ```prep_coords!```.
+const CVRP_SECTIONS: &str = "DEMAND_SECTION\n1 0\n2 10\n3 20\nDEPOT_SECTION\n1\n-1";
+
+/// Prepares an instance of the given type with explicit edge weights.
+macro_rules! prep_matrix {
+    ($kind:expr, $dim:expr, $format:expr, $w:expr) => {
+        format!(
+            "
+        NAME: test
+        TYPE: {}
+        DIMENSION: {}
+        EDGE_WEIGHT_TYPE: EXPLICIT
+        EDGE_WEIGHT_FORMAT: {}
+        EDGE_WEIGHT_SECTION
+        {}
+        EOF
+        ",
+            $kind, $dim, $format, $w
+        )
+    };
+}
+
 fn test_weight(tsp: Tsp) {
     assert_relative_eq!(5_f64, tsp.weight(1, 2));
     assert_relative_eq!(10., tsp.weight(4, 3));
@@ -219,6 +270,23 @@ fn test_tour() {
     assert_eq!(2, tsp.tours().len());
 }
 
+#[test]
+fn test_tour_comma_separated() {
+    let s = "
+    NAME : test
+    TYPE : TOUR
+    DIMENSION : 4
+    TOUR_SECTION
+    4,3,2,1,-1
+    1, 2, 3, 4 ,-1
+    -1
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(&vec![vec![4, 3, 2, 1], vec![1, 2, 3, 4]], tsp.tours());
+}
+
 #[test]
 fn test_metric_fn() {
     assert_eq!(5., euc_2d(&vec![6., 0.], &vec![3., 4.]), "Test euc_2d");
@@ -254,3 +322,2039 @@ fn test_metric_fn() {
         "Test xray2"
     );
 }
+
+#[test]
+fn test_capacity_float() {
+    let s = prep_coords!("TYPE: CVRP\nCAPACITY: 6000.0", CVRP_SECTIONS);
+
+    let result = TspBuilder::parse_str(&s);
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    assert_relative_eq!(6000., result.unwrap().capacity());
+
+    let result = TspBuilder::parse_str(s.replace("6000.0", "many"));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_depot_section() {
+    let s = prep_coords!(
+        "TYPE: CVRP\nCAPACITY: 100",
+        "DEMAND_SECTION\n1 0\n2 0\n3 5\nDEPOT_SECTION\n1\n\n2\n-1\n-1"
+    );
+
+    let tsp = TspBuilder::parse_str(&s).unwrap();
+    assert_eq!(&HashSet::from([1, 2]), tsp.depots());
+
+    let result = TspBuilder::parse_str(s.replace("\n2\n-1", "\ndepot\n-1"));
+    assert!(matches!(result, Err(ParseTspError::InvalidInput { .. })));
+
+    let result = TspBuilder::parse_str(s.replace("-1", "").replace("EOF", ""));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_capacity_non_cvrp() {
+    let (_, warnings) = TspBuilder::new()
+        .load_str(TEST_STR)
+        .and_then(|b| b.build_with_warnings())
+        .unwrap();
+    assert!(warnings.is_empty());
+
+    let (_, warnings) = TspBuilder::new()
+        .load_str(TEST_STR.replace("DIMENSION: 3", "DIMENSION: 3\nCAPACITY: 100"))
+        .and_then(|b| b.build_with_warnings())
+        .unwrap();
+    assert_eq!(
+        vec![String::from("CAPACITY: ignored for type TSP")],
+        warnings
+    );
+}
+
+#[test]
+fn test_spec_inline_comment() {
+    let tsp = TspBuilder::parse_str(TEST_STR.replace(
+        "DIMENSION: 3",
+        "DIMENSION: 3  # number of cities\nSCALE: 10 % ignored",
+    ))
+    .unwrap();
+    assert_eq!(3, tsp.dim());
+    assert_eq!(Some(10.), tsp.scale_factor());
+
+    let tsp = TspBuilder::parse_str(TEST_STR.replace("test", "test # 1")).unwrap();
+    assert_eq!("test # 1", tsp.name());
+
+    let result = TspBuilder::parse_str(TEST_STR.replace("DIMENSION: 3", "DIMENSION: # 3"));
+    assert!(matches!(result, Err(ParseTspError::InvalidInput { .. })));
+}
+
+#[test]
+fn test_section_order() {
+    let s = "
+    NAME : test
+    TYPE : CVRP
+    DIMENSION : 3
+    EDGE_WEIGHT_TYPE : EUC_2D
+    CAPACITY : 100
+    DEPOT_SECTION
+    1
+    -1
+    DEMAND_SECTION
+    1 0
+
+    2 10
+    3 20
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 4
+    3 6 8
+    EOF
+    ";
+
+    let (tsp, warnings) = TspBuilder::new()
+        .load_str(s)
+        .and_then(|b| b.build_with_warnings())
+        .unwrap();
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    assert_eq!(3, tsp.node_coords().len());
+    assert_eq!(Some(&20.), tsp.demands().get(&3));
+    assert!(tsp.depots().contains(&1));
+    assert!(tsp.check_consistency().is_empty());
+    assert_relative_eq!(5., tsp.weight(1, 2));
+}
+
+#[test]
+fn test_cvrp_requires_demands() {
+    let s = "
+    NAME : test
+    TYPE : CVRP
+    DIMENSION : 3
+    EDGE_WEIGHT_TYPE : EXPLICIT
+    EDGE_WEIGHT_FORMAT : UPPER_ROW
+    CAPACITY : 10
+    EDGE_WEIGHT_SECTION
+    1 2
+    3
+    DEPOT_SECTION
+    1
+    -1
+    EOF
+    ";
+
+    match TspBuilder::parse_str(s) {
+        Err(ParseTspError::MissingEntry(key)) => assert_eq!("DEMAND_SECTION", key),
+        r => panic!("unexpected result: {:?}", r),
+    }
+
+    let s = s.replace(
+        "DEPOT_SECTION",
+        "DEMAND_SECTION\n1 0\n2 4\n3 5\nDEPOT_SECTION",
+    );
+    assert!(TspBuilder::parse_str(s).is_ok());
+}
+
+#[test]
+fn test_depots_ordered() {
+    let s = "
+    NAME : test
+    TYPE : CVRP
+    DIMENSION : 4
+    EDGE_WEIGHT_TYPE : EUC_2D
+    CAPACITY : 100
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 4
+    3 6 8
+    4 9 12
+    DEMAND_SECTION
+    1 0
+    2 5
+    3 0
+    4 0
+    DEPOT_SECTION
+    3
+    1
+    4
+    -1
+    EOF
+    ";
+
+    let mut tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(vec![3, 1, 4], tsp.depots_ordered());
+    assert_eq!(3, tsp.depots().len());
+
+    tsp.depots_mut().remove(&1);
+    tsp.depots_mut().insert(2);
+    assert_eq!(vec![3, 4, 2], tsp.depots_ordered());
+}
+
+#[test]
+fn test_decimal_comma() {
+    let s = "
+    NAME : test
+    TYPE : CVRP
+    DIMENSION : 2
+    EDGE_WEIGHT_TYPE : EUC_2D
+    CAPACITY : 10
+    NODE_COORD_SECTION
+    1 38,24 20,42
+    2 39,57 26,15
+    DEMAND_SECTION
+    1 0
+    2 1,5
+    EOF
+    ";
+
+    let tsp = TspBuilder::new().decimal_comma(true).read_str(s).unwrap();
+    assert_eq!(
+        &vec![38.24, 20.42],
+        tsp.node_coords().get(&1).unwrap().pos()
+    );
+    assert_eq!(Some(&1.5), tsp.demands().get(&2));
+
+    let s = prep_weight!(WeightFormat::UpperRow.tsp_str(), "1,5 2 3 4 5 6 7 8 9 10");
+    assert!(TspBuilder::parse_str(&s).is_err());
+    let tsp = TspBuilder::new().decimal_comma(true).read_str(&s).unwrap();
+    assert_relative_eq!(1.5, tsp.weight(0, 1));
+}
+
+#[test]
+fn test_check_consistency() {
+    let s = prep_coords!("TYPE: CVRP\nCAPACITY: 100", CVRP_SECTIONS);
+
+    let mut tsp = TspBuilder::parse_str(s).unwrap();
+    assert!(tsp.check_consistency().is_empty());
+
+    tsp.node_coords_mut().remove(&3);
+    tsp.demands_mut().remove(&2);
+    tsp.depots_mut().insert(7);
+    tsp.tours_mut().push(vec![1, 2, 2]);
+    tsp.fixed_edges_mut().push((1, 4));
+
+    let problems = tsp.check_consistency();
+    assert_eq!(5, problems.len(), "{:?}", problems);
+
+    let s = prep_weight!(WeightFormat::UpperRow.tsp_str(), "1 2 3 4 5 6 7 8 9 10");
+    let mut tsp = TspBuilder::parse_str(s).unwrap();
+    assert!(tsp.check_consistency().is_empty());
+
+    tsp.edge_weights_mut()[1].pop();
+    assert_eq!(1, tsp.check_consistency().len());
+}
+
+#[test]
+fn test_explicit_function() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 3
+    EDGE_WEIGHT_TYPE: EXPLICIT
+    EDGE_WEIGHT_FORMAT: FUNCTION
+    EOF
+    ";
+
+    let result = TspBuilder::parse_str(s);
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    let tsp = result.unwrap();
+    assert_eq!(WeightFormat::Function, tsp.weight_format());
+    assert!(tsp.edge_weights().is_empty());
+    assert_relative_eq!(0., tsp.weight(1, 2));
+
+    let result = TspBuilder::parse_str(s.replace("FUNCTION", "FULL_MATRIX"));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_to_tsplib_string() {
+    fn assert_round_trip(tsp: &Tsp) {
+        let s = tsp.to_tsplib_string();
+        let result = TspBuilder::parse_str(&s);
+        assert!(result.is_ok(), "{}\n{}", result.err().unwrap(), s);
+        let other = result.unwrap();
+        assert_eq!(tsp.name(), other.name());
+        assert_eq!(tsp.kind(), other.kind());
+        assert_eq!(tsp.dim(), other.dim());
+        assert_eq!(tsp.weight_kind(), other.weight_kind());
+        assert_eq!(tsp.weight_format(), other.weight_format());
+        assert_eq!(tsp.edge_weights(), other.edge_weights());
+        assert_eq!(tsp.tours(), other.tours());
+        assert_eq!(tsp.node_coords().len(), other.node_coords().len());
+        for (id, pt) in tsp.node_coords() {
+            assert_eq!(pt.pos(), other.node_coords().get(id).unwrap().pos());
+        }
+        for a in tsp.node_ids() {
+            for b in tsp.node_ids() {
+                assert_eq!(tsp.weight(a, b), other.weight(a, b));
+            }
+        }
+    }
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert_round_trip(&tsp);
+    assert_round_trip(&TspBuilder::parse_str(TEST_STR.replace("GEO", "EUC_2D")).unwrap());
+
+    let s = TEST_STR
+        .replace("GEO", "EUC_3D")
+        .replace("20.42", "20.42 1.5")
+        .replace("26.15", "26.15 2.5")
+        .replace("25.32", "25.32 3.5");
+    let tsp = TspBuilder::parse_str(&s).unwrap();
+    assert_round_trip(&tsp);
+    assert!(tsp.to_tsplib_string().contains("3 40.56 25.32 3.5\n"));
+
+    let matrix = "0 5 2 3 4 5 0 5 9 7 2 5 0 10 9 3 9 10 0 10 4 7 9 10 0";
+    let tsp = TspBuilder::parse_str(prep_weight!("FULL_MATRIX", matrix)).unwrap();
+    assert_round_trip(&tsp);
+    for format in ["UPPER_ROW", "LOWER_DIAG_ROW", "UPPER_DIAG_COL"] {
+        let explicit = tsp.to_explicit(WeightFormat::from(format)).unwrap();
+        assert!(explicit
+            .to_tsplib_string()
+            .contains(&format!("EDGE_WEIGHT_FORMAT: {}", format)));
+        assert_round_trip(&explicit);
+    }
+
+    let tsp = TspBuilder::parse_str(HCP_STR).unwrap();
+    let other = TspBuilder::parse_str(tsp.to_tsplib_string()).unwrap();
+    assert_eq!(tsp.edge_format(), other.edge_format());
+}
+
+#[test]
+fn test_write_weight_format() {
+    let matrix = "0 5 2 3 4 5 0 5 9 7 2 5 0 10 9 3 9 10 0 10 4 7 9 10 0";
+    let tsp = TspBuilder::parse_str(prep_weight!("FULL_MATRIX", matrix)).unwrap();
+    let mut buf = Vec::new();
+    let options = WriteOptions::new().weight_format(WeightFormat::UpperRow);
+    tsp.write_tsplib_with(&mut buf, &options).unwrap();
+    let s = String::from_utf8(buf).unwrap();
+    assert!(s.contains("EDGE_WEIGHT_FORMAT: UPPER_ROW\n"), "{}", s);
+    assert!(
+        s.contains("EDGE_WEIGHT_SECTION\n5 2 3 4\n5 9 7\n10 9\n10\n"),
+        "{}",
+        s
+    );
+
+    let other = TspBuilder::parse_str(&s).unwrap();
+    assert_eq!(WeightFormat::UpperRow, other.weight_format());
+    for a in 0..5 {
+        for b in 0..5 {
+            assert_eq!(tsp.weight(a, b), other.weight(a, b));
+        }
+    }
+
+    // Coordinate instances are converted to explicit weights.
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    let mut buf = Vec::new();
+    tsp.write_tsplib_with(&mut buf, &options).unwrap();
+    // The rows of the written matrix refer to the ids from 1.
+    let other = TspBuilder::new()
+        .matrix_indexing(MatrixIndexing::OneBased)
+        .read_str(String::from_utf8(buf).unwrap())
+        .unwrap();
+    assert_eq!(WeightKind::Explicit, other.weight_kind());
+    assert_eq!(WeightFormat::UpperRow, other.weight_format());
+    for a in 1..=3 {
+        // UPPER_ROW has no diagonal, whereas GEO yields a distance of 1 from a node to itself.
+        for b in (1..=3).filter(|&b| b != a) {
+            assert_eq!(tsp.weight(a, b), other.weight(a, b));
+        }
+    }
+
+    let tsp =
+        TspBuilder::parse_str(prep_weight!("FULL_MATRIX", matrix.replacen("5", "6", 1))).unwrap();
+    let result = tsp.write_tsplib_with(&mut Vec::new(), &options);
+    assert!(matches!(result, Err(ParseTspError::InvalidInput { .. })));
+    let options = WriteOptions::new().weight_format(WeightFormat::FullMatrix);
+    assert!(tsp.write_tsplib_with(&mut Vec::new(), &options).is_ok());
+}
+
+#[test]
+fn test_write_precision() {
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    let mut out = Vec::new();
+    tsp.write_tsplib(&mut out).unwrap();
+    let s = String::from_utf8(out).unwrap();
+    assert!(s.contains("1 38.24 20.42\n"), "{}", s);
+
+    let result = TspBuilder::parse_str(&s).unwrap();
+    for (id, pt) in tsp.node_coords() {
+        assert_eq!(pt.pos(), result.node_coords().get(id).unwrap().pos());
+    }
+
+    let mut out = Vec::new();
+    tsp.write_xy_with(&mut out, &WriteOptions::new().precision(1))
+        .unwrap();
+    assert_eq!(
+        "38.2 20.4\n39.6 26.1\n40.6 25.3\n",
+        String::from_utf8(out).unwrap()
+    );
+
+    assert_eq!("20.42", fmt_f64(20.42 + 1e-12, Some(6)));
+    assert_eq!("565", fmt_f64(565., Some(6)));
+    assert_eq!("0", fmt_f64(-0.0001, Some(2)));
+}
+
+#[test]
+fn test_tour_edges() {
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    let tour = vec![3, 1, 2];
+    let edges: Vec<(usize, usize)> = tsp.tour_edges(&tour).collect();
+    assert_eq!(tour.len(), edges.len());
+    assert_eq!(vec![(3, 1), (1, 2), (2, 3)], edges);
+    assert_eq!(0, tsp.tour_edges(&[]).count());
+}
+
+#[test]
+fn test_successors() {
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    let succ = tsp.tour_to_successors(&[2, 3, 1]);
+    assert_eq!(vec![None, Some(2), Some(3), Some(1)], succ);
+    assert_eq!(vec![1, 2, 3], tsp.successors_to_tour(&succ));
+
+    let tour = vec![1, 3, 2];
+    assert_eq!(tour, tsp.successors_to_tour(&tsp.tour_to_successors(&tour)));
+    assert!(tsp.successors_to_tour(&[None, None, None, None]).is_empty());
+
+    // Node 0 is a valid id, e.g. with zero-based explicit weights.
+    let tour = vec![0, 1, 2];
+    let succ = tsp.tour_to_successors(&tour);
+    assert_eq!(Some(1), succ[0]);
+    assert_eq!(tour, tsp.successors_to_tour(&succ));
+}
+
+#[test]
+fn test_keyword_round_trip() {
+    macro_rules! assert_round_trip {
+        ($enm:ident, [$($variant:ident),+]) => {
+            $(
+                let kind = $enm::$variant;
+                assert_eq!(kind, kind.tsp_str().parse::<$enm>().unwrap(), "{}", kind);
+                assert_eq!(kind, $enm::from(kind.tsp_str()), "{}", kind);
+            )+
+            assert!("UNDEFINED".parse::<$enm>().is_err());
+            assert!("unknown".parse::<$enm>().is_err());
+        };
+    }
+
+    assert_round_trip!(TspKind, [Tsp, Atsp, Sop, Hcp, Cvrp, Gtsp, Tour]);
+    assert_round_trip!(
+        WeightKind,
+        [
+            Explicit, Euc2d, Euc3d, Max2d, Max3d, Man2d, Man3d, Ceil2d, Geo, Att, Xray1, Xray2,
+            Custom
+        ]
+    );
+    assert_round_trip!(
+        WeightFormat,
+        [
+            Function,
+            FullMatrix,
+            UpperRow,
+            LowerRow,
+            UpperDiagRow,
+            LowerDiagRow,
+            UpperCol,
+            LowerCol,
+            UpperDiagCol,
+            LowerDiagCol
+        ]
+    );
+    assert_round_trip!(CoordKind, [Coord2d, Coord3d, NoCoord]);
+    assert_round_trip!(DisplayKind, [DispCoo, Disp2d, NoDisp]);
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert_eq!("GEO", tsp.weight_kind_str());
+    assert_eq!(tsp.weight_kind(), tsp.weight_kind_str().parse().unwrap());
+}
+
+#[test]
+fn test_weight_kind_unit() {
+    let kinds = [
+        (WeightKind::Explicit, "unknown", false),
+        (WeightKind::Euc2d, "units", true),
+        (WeightKind::Euc3d, "units", true),
+        (WeightKind::Max2d, "units", false),
+        (WeightKind::Max3d, "units", false),
+        (WeightKind::Man2d, "units", false),
+        (WeightKind::Man3d, "units", false),
+        (WeightKind::Ceil2d, "units", true),
+        (WeightKind::Geo, "km", false),
+        (WeightKind::Att, "units", false),
+        (WeightKind::Xray1, "unknown", false),
+        (WeightKind::Xray2, "unknown", false),
+        (WeightKind::Custom, "unknown", false),
+        (WeightKind::Undefined, "unknown", false),
+    ];
+
+    for (kind, unit, euclidean) in kinds.iter() {
+        assert_eq!(*unit, kind.unit_hint(), "{}", kind);
+        assert_eq!(*kind == WeightKind::Geo, kind.is_geographic(), "{}", kind);
+        assert_eq!(*euclidean, kind.is_euclidean(), "{}", kind);
+    }
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert!(!tsp.is_euclidean());
+    let tsp = TspBuilder::parse_str(TEST_STR.replace("GEO", "EUC_2D")).unwrap();
+    assert!(tsp.is_euclidean());
+}
+
+#[test]
+fn test_equals_separator() {
+    let s = "
+    NAME = test
+    TYPE = TSP
+    COMMENT = a = b : c
+    DIMENSION = 3
+    EDGE_WEIGHT_TYPE = EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 4
+    3 6 8
+    EOF
+    ";
+
+    let result = TspBuilder::parse_str(s);
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    let tsp = result.unwrap();
+    assert_eq!("test", tsp.name());
+    assert_eq!("a = b : c", tsp.comment());
+    assert_eq!(3, tsp.dim());
+    assert_eq!(WeightKind::Euc2d, tsp.weight_kind());
+    assert_relative_eq!(5., tsp.weight(1, 2));
+}
+
+#[test]
+fn test_node_coord_malformed() {
+    let result = TspBuilder::parse_str(TEST_STR.replace("2 39.57 26.15", "2 39.57"));
+    match result {
+        Err(ParseTspError::InvalidEntry(line)) => assert_eq!("2 39.57", line),
+        other => panic!("{:?}", other.map(|t| t.dim())),
+    }
+
+    let s = TEST_STR
+        .replace("GEO", "EUC_3D")
+        .replace("20.42", "20.42 1")
+        .replace("26.15", "26.15 2");
+    let result = TspBuilder::parse_str(&s);
+    assert!(matches!(result, Err(ParseTspError::InvalidEntry(_))));
+
+    match TspBuilder::parse_str(TEST_STR.replace("26.15", "x")) {
+        Err(ParseTspError::InvalidInput { key, val }) => {
+            assert_eq!("NODE_COORD_SECTION", key);
+            assert_eq!("x", val);
+        }
+        other => panic!("{:?}", other.map(|t| t.dim())),
+    }
+
+    let result = TspBuilder::parse_str(TEST_STR.replace("DIMENSION: 3", "DIMENSION: 4"));
+    assert!(matches!(result, Err(ParseTspError::InvalidEntry(_))));
+
+    let truncated = &TEST_STR[..TEST_STR.find("3 40.56").unwrap()];
+    let result = TspBuilder::parse_str(truncated);
+    assert!(matches!(result, Err(ParseTspError::InvalidEntry(_))));
+
+    let result = TspBuilder::new()
+        .implicit_ids(true)
+        .read_str(TEST_STR.replace("2 39.57 26.15", "39.57"));
+    assert!(matches!(result, Err(ParseTspError::InvalidEntry(_))));
+}
+
+#[test]
+fn test_invalid_node_id() {
+    for id in ["1.0", "+1", "-1", "0", "1e0"] {
+        let result = TspBuilder::parse_str(TEST_STR.replace("1 38.24", &format!("{} 38.24", id)));
+        match result {
+            Err(ParseTspError::InvalidInput { key, val }) => {
+                assert_eq!("node id", key);
+                assert_eq!(id, val);
+            }
+            other => panic!("{}: {:?}", id, other.map(|t| t.dim())),
+        }
+    }
+}
+
+#[test]
+fn test_implicit_ids() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 3
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    0 0
+    3 4
+    6 8
+    EOF
+    ";
+
+    let result = TspBuilder::new().implicit_ids(true).read_str(s);
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    let tsp = result.unwrap();
+    assert_eq!(3, tsp.node_coords().len());
+    assert_eq!(&vec![6., 8.], tsp.node_coords().get(&3).unwrap().pos());
+    assert_relative_eq!(10., tsp.weight(1, 3));
+}
+
+#[test]
+fn test_map_coords() {
+    let s = prep_coords!("TYPE: TSP", "");
+
+    let mut tsp = TspBuilder::parse_str(s).unwrap();
+    assert_relative_eq!(5., tsp.weight(1, 2));
+
+    tsp.map_coords(|p| p.iter().map(|x| 2. * x).collect());
+    assert_eq!(&vec![12., 16.], tsp.node_coords().get(&3).unwrap().pos());
+    assert_relative_eq!(10., tsp.weight(1, 2));
+    assert_eq!(CoordKind::Coord2d, tsp.coord_kind());
+
+    tsp.map_coords(|p| vec![p[0], p[1], 1.]);
+    assert_eq!(CoordKind::Coord3d, tsp.coord_kind());
+}
+
+#[test]
+fn test_map_coords_invalidates_cache() {
+    let mut tsp = TspBuilder::new()
+        .preserve_raw(true)
+        .read_str(TEST_STR.replace("GEO", "EUC_2D"))
+        .unwrap();
+    let w = tsp.weight(1, 2);
+    let (_, max) = tsp.weight_bounds();
+
+    tsp.map_coords(|p| vec![p[0] + 3., p[1] * 2.]);
+    let (a, b) = (
+        tsp.node_coords().get(&1).unwrap().pos(),
+        tsp.node_coords().get(&2).unwrap().pos(),
+    );
+    let expected = (a[0] - b[0]).hypot(a[1] - b[1]);
+    assert_relative_eq!(expected, tsp.weight(1, 2));
+    assert!((w - tsp.weight(1, 2)).abs() > 1.);
+    assert!(tsp.weight_bounds().1 > max);
+
+    let mut buf = Vec::new();
+    tsp.write_tsplib(&mut buf).unwrap();
+    let s = String::from_utf8(buf).unwrap();
+    assert!(s.contains("1 41.24 40.84"));
+}
+
+const HCP_STR: &str = "
+NAME: test
+TYPE: HCP
+DIMENSION: 5
+EDGE_DATA_FORMAT: EDGE_LIST
+EDGE_DATA_SECTION
+1 2
+2 3
+3 4
+4 5
+5 1
+1 3
+-1
+EOF
+";
+
+#[test]
+fn test_edge_list() {
+    let tsp = TspBuilder::parse_str(HCP_STR).unwrap();
+    let edges = tsp.edge_list();
+    assert_eq!(6, edges.len());
+    assert_eq!((1, 2), (edges[0].0, edges[0].1));
+    assert_eq!((1, 3), (edges[5].0, edges[5].1));
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    let edges = tsp.edge_list();
+    assert_eq!(3, edges.len());
+    assert_relative_eq!(tsp.weight(2, 3), edges[2].2);
+}
+
+#[cfg(feature = "petgraph")]
+#[test]
+fn test_to_petgraph() {
+    let graph = TspBuilder::parse_str(HCP_STR).unwrap().to_petgraph();
+    assert_eq!(5, graph.node_count());
+    assert_eq!(6, graph.edge_count());
+    let (a, b) = graph
+        .edge_endpoints(graph.edge_indices().next_back().unwrap())
+        .unwrap();
+    assert_eq!((1, 3), (graph[a], graph[b]));
+}
+
+#[test]
+fn test_edge_list_without_terminator() {
+    let tsp = TspBuilder::parse_str(HCP_STR.replace("-1\n", "")).unwrap();
+    assert_eq!(6, tsp.edge_list().len());
+
+    let tsp = TspBuilder::parse_str(HCP_STR.replace("-1\nEOF\n", "")).unwrap();
+    assert_eq!(6, tsp.edge_list().len());
+
+    let (tsp, _) = TspBuilder::new()
+        .capture_trailer(true)
+        .load_str(HCP_STR.replace("-1\nEOF\n", "EOF\n1 4\n"))
+        .and_then(|b| b.build_with_warnings())
+        .unwrap();
+    assert_eq!(6, tsp.edge_list().len());
+    assert_eq!("1 4", tsp.trailer());
+}
+
+#[test]
+fn test_weight_single_line() {
+    let matrix = "0 1 2 3 4 1 0 5 6 7 2 5 0 8 9 3 6 8 0 10 4 7 9 10 0";
+    let result = TspBuilder::parse_str(prep_weight!(WeightFormat::FullMatrix.tsp_str(), matrix));
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    test_weight(result.unwrap());
+
+    let result = TspBuilder::parse_str(prep_weight!(
+        WeightFormat::UpperRow.tsp_str(),
+        "1 2 3 4 5 6 7 8 9 10"
+    ));
+    test_weight(result.unwrap());
+
+    let sop = |m: &str| {
+        format!(
+            "
+            NAME: test
+            TYPE: SOP
+            DIMENSION: 5
+            EDGE_WEIGHT_TYPE: EXPLICIT
+            EDGE_WEIGHT_FORMAT: FULL_MATRIX
+            EDGE_WEIGHT_SECTION
+            {}
+            EOF
+            ",
+            m
+        )
+    };
+
+    // The dimension precedes the matrix on the same line.
+    let result = TspBuilder::parse_str(sop(&format!("5 {}", matrix)));
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    test_weight(result.unwrap());
+
+    // The dimension is given on a separate line.
+    let result = TspBuilder::parse_str(sop(&format!("5\n{}", matrix)));
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    test_weight(result.unwrap());
+}
+
+#[test]
+fn test_path_length() {
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    let path = [1, 2, 3];
+    assert_relative_eq!(tsp.weight(1, 2) + tsp.weight(2, 3), tsp.path_length(&path));
+    assert_relative_eq!(
+        tsp.tour_length(&path) - tsp.path_length(&path),
+        tsp.weight(3, 1),
+        epsilon = 1e-9
+    );
+    assert_relative_eq!(0., tsp.path_length(&[2]));
+}
+
+#[test]
+fn test_multi_depot() {
+    let s = "
+    NAME : test
+    TYPE : CVRP
+    DIMENSION : 5
+    EDGE_WEIGHT_TYPE : EUC_2D
+    CAPACITY : 100
+    NODE_COORD_SECTION
+    1 0 0
+    2 1 0
+    3 4 0
+    4 5 0
+    5 10 0
+    DEMAND_SECTION
+    1 0
+    2 10
+    3 20
+    4 0
+    5 30
+    DEPOT_SECTION
+    1
+    4
+    -1
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    let dists = tsp.depot_distances();
+    assert_eq!(2, dists.len());
+    assert_eq!(4, dists[&1].len());
+    assert_relative_eq!(10., dists[&1][&5]);
+    assert_relative_eq!(5., dists[&4][&5]);
+    assert_relative_eq!(1., dists[&4][&3]);
+
+    assert_eq!(Some(1), tsp.nearest_depot(2));
+    assert_eq!(Some(4), tsp.nearest_depot(3));
+    assert_eq!(Some(4), tsp.nearest_depot(5));
+    assert_eq!(Some(4), tsp.nearest_depot(4));
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert!(tsp.depot_distances().is_empty());
+    assert_eq!(None, tsp.nearest_depot(1));
+}
+
+#[test]
+fn test_tour_display_data() {
+    let s = "
+    NAME : test
+    TYPE : TOUR
+    DISPLAY_DATA_TYPE : TWOD_DISPLAY
+    DISPLAY_DATA_SECTION
+    1 0 0
+    2 3 4
+    3 6 8
+    TOUR_SECTION
+    1 2 3
+    -1
+    EOF
+    ";
+
+    let result = TspBuilder::parse_str(s);
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    let tsp = result.unwrap();
+    assert_eq!(3, tsp.disp_coords().len());
+    assert_eq!(1, tsp.tours().len());
+
+    let result = TspBuilder::parse_str(s.replace("TOUR_SECTION", "-1\nTOUR_SECTION"));
+    assert_eq!(3, result.unwrap().disp_coords().len());
+
+    // Malformed rows are reported with or without a dimension.
+    for s in [
+        s.to_string(),
+        s.replace("DISPLAY_DATA_SECTION", "DIMENSION: 3\nDISPLAY_DATA_SECTION"),
+    ] {
+        let result = TspBuilder::parse_str(s.replace("2 3 4", "2 3"));
+        assert!(
+            matches!(&result, Err(ParseTspError::InvalidEntry(e)) if e == "2 3"),
+            "{:?}",
+            result
+        );
+        match TspBuilder::parse_str(s.replace("2 3 4", "2 3 x")) {
+            Err(ParseTspError::InvalidInput { key, val }) => {
+                assert_eq!("DISPLAY_DATA_SECTION", key);
+                assert_eq!("x", val);
+            }
+            result => panic!("expected an invalid coordinate: {:?}", result),
+        }
+    }
+
+    let s = s.replace("DISPLAY_DATA_SECTION", "DIMENSION: 4\nDISPLAY_DATA_SECTION");
+    let result = TspBuilder::parse_str(s.replace("TOUR_SECTION\n    1 2 3\n    -1\n", ""));
+    assert!(
+        matches!(&result, Err(ParseTspError::InvalidEntry(e)) if e.contains("DISPLAY_DATA_SECTION")),
+        "{:?}",
+        result
+    );
+}
+
+#[test]
+fn test_build_with_warnings() {
+    // Node 2 has a stray token and the depot has a demand.
+    let s = prep_coords!(
+        "TYPE: CVRP\nCAPACITY: 100",
+        CVRP_SECTIONS.replace("SECTION\n1 0", "SECTION\n1 5")
+    )
+    .replace("2 3 4", "2 3 4 stray");
+
+    let result = TspBuilder::new()
+        .load_str(s)
+        .and_then(|b| b.build_with_warnings());
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    let (tsp, warnings) = result.unwrap();
+    assert_eq!(3, tsp.dim());
+    assert_eq!(2, warnings.len(), "{:?}", warnings);
+    assert!(warnings[0].contains("node 2"));
+    assert!(warnings[1].contains("depot 1"));
+
+    let (_, warnings) = TspBuilder::new()
+        .load_str(TEST_STR)
+        .and_then(|b| b.build_with_warnings())
+        .unwrap();
+    assert!(warnings.is_empty());
+}
+
+#[test]
+fn test_convex_hull() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 7
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 2 2
+    2 0 0
+    3 4 0
+    4 1 1
+    5 4 4
+    6 2 0
+    7 0 4
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(vec![2, 3, 5, 7], tsp.convex_hull());
+
+    // Invalid coordinates must not cause a panic.
+    let tsp = TspBuilder::parse_str(s.replace("1 2 2", "1 NaN 2")).unwrap();
+    assert!(!tsp.convex_hull().contains(&4));
+
+    let tsp = TspBuilder::parse_str(prep_weight!(
+        WeightFormat::UpperRow.tsp_str(),
+        "1 2 3 4 5 6 7 8 9 10"
+    ))
+    .unwrap();
+    assert!(tsp.convex_hull().is_empty());
+}
+
+#[test]
+fn test_time_windows() {
+    let s = prep_coords!(
+        "TYPE: CVRP\nCAPACITY: 100",
+        format!(
+            "{}\nTIME_WINDOW_SECTION\n1 0 1000\n2 10 50.5\n3 30 90",
+            CVRP_SECTIONS
+        )
+    );
+
+    let result = TspBuilder::parse_str(&s);
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    let tsp = result.unwrap();
+    assert_eq!(3, tsp.time_windows().len());
+    assert_eq!(Some(&(10., 50.5)), tsp.time_windows().get(&2));
+    assert_eq!(1, tsp.depots().len());
+
+    assert!(TspBuilder::parse_str(TEST_STR)
+        .unwrap()
+        .time_windows()
+        .is_empty());
+
+    let result = TspBuilder::parse_str(s.replace("2 10 50.5\n3 30 90", ""));
+    assert!(
+        matches!(&result, Err(ParseTspError::InvalidEntry(e)) if e.contains("TIME_WINDOW_SECTION")),
+        "{:?}",
+        result
+    );
+    let result = TspBuilder::parse_str(s.replace("3 30 90", "3 10"));
+    assert!(
+        matches!(&result, Err(ParseTspError::InvalidEntry(e)) if e == "3 10"),
+        "{:?}",
+        result
+    );
+}
+
+#[test]
+fn test_density() {
+    let tsp = TspBuilder::parse_str(HCP_STR).unwrap();
+    assert_eq!(6, tsp.edge_count());
+    assert_relative_eq!(0.6, tsp.density());
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert_eq!(3, tsp.edge_count());
+    assert_relative_eq!(1., tsp.density());
+}
+
+#[test]
+fn test_geo_lon_lat_order() {
+    let lat_lon = TspBuilder::parse_str(TEST_STR).unwrap();
+    let lon_lat = TspBuilder::new()
+        .geo_lon_lat_order(true)
+        .read_str(TEST_STR)
+        .unwrap();
+    assert!(!lat_lon.geo_lon_lat());
+    assert!(lon_lat.geo_lon_lat());
+
+    let d1 = lat_lon.weight(1, 2);
+    let d2 = lon_lat.weight(1, 2);
+    assert!((d1 - d2).abs() > 1., "{} {}", d1, d2);
+    assert_relative_eq!(d1, geo(&[38.24, 20.42], &[39.57, 26.15]));
+    assert_relative_eq!(d2, geo(&[20.42, 38.24], &[26.15, 39.57]));
+}
+
+#[test]
+fn test_edge_weight_stats() {
+    let tsp = TspBuilder::parse_str(prep_weight!(
+        WeightFormat::UpperRow.tsp_str(),
+        "1 2 3 4 5 6 7 8 9 10"
+    ))
+    .unwrap();
+    let (min, max, mean, std) = tsp.edge_weight_stats();
+    assert_relative_eq!(1., min);
+    assert_relative_eq!(10., max);
+    assert_relative_eq!(5.5, mean);
+    assert_relative_eq!(8.25_f64.sqrt(), std, epsilon = 1e-12);
+}
+
+#[test]
+fn test_headerless() {
+    let s = "
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 4
+    3 6 8
+    4 0 8
+    EOF
+    ";
+
+    let result = TspBuilder::parse_str_headerless(s);
+    assert!(result.is_ok(), "{}", result.err().unwrap());
+    let tsp = result.unwrap();
+    assert_eq!(4, tsp.dim());
+    assert_eq!(TspKind::Tsp, tsp.kind());
+    assert_eq!(WeightKind::Euc2d, tsp.weight_kind());
+    assert_relative_eq!(6., tsp.weight(3, 4));
+
+    let tsp = TspBuilder::parse_str_headerless("NODE_COORD_SECTION\n1 0 0 0\n2 1 2 2\n").unwrap();
+    assert_eq!(WeightKind::Euc3d, tsp.weight_kind());
+    assert_relative_eq!(3., tsp.weight(1, 2));
+
+    assert!(TspBuilder::parse_str_headerless("1 0 0\n").is_err());
+}
+
+#[test]
+fn test_round_weight() {
+    assert_relative_eq!(3., WeightKind::Euc2d.round_weight(2.5));
+    assert_relative_eq!(2., WeightKind::Euc2d.round_weight(2.4));
+    assert_relative_eq!(3., WeightKind::Ceil2d.round_weight(2.1));
+    assert_relative_eq!(2., WeightKind::Geo.round_weight(2.9));
+    assert_relative_eq!(3., WeightKind::Att.round_weight(2.4));
+    assert_relative_eq!(3., WeightKind::Att.round_weight(2.6));
+}
+
+#[test]
+fn test_integer_weights() {
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    for (a, b) in [(1, 2), (1, 3), (2, 3)] {
+        assert_eq!(tsp.rounded_weight(a, b), tsp.weight_i32(a, b) as f64);
+        assert_eq!(tsp.rounded_weight(a, b), tsp.weight_i64(a, b) as f64);
+    }
+
+    let s = prep_matrix!("ATSP", 2, "FULL_MATRIX", "0 1e12\n-3.6 0");
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(i32::MAX, tsp.weight_i32(0, 1));
+    assert_eq!(1_000_000_000_000, tsp.weight_i64(0, 1));
+    assert_eq!(-4, tsp.weight_i32(1, 0));
+}
+
+#[test]
+fn test_upper_triangular_weights() {
+    let s = prep_weight!(WeightFormat::UpperRow.tsp_str(), "1 2 3 4 5 6 7 8 9 10");
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    let v = tsp.upper_triangular_weights();
+    assert_eq!(tsp.dim() * (tsp.dim() - 1) / 2, v.len());
+    assert_eq!((1..=10).map(f64::from).collect::<Vec<_>>(), v);
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert_eq!(
+        vec![tsp.weight(1, 2), tsp.weight(1, 3), tsp.weight(2, 3)],
+        tsp.upper_triangular_weights()
+    );
+}
+
+#[test]
+fn test_rounding_policy() {
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert_eq!(RoundingPolicy::Truncate, tsp.rounding_policy());
+    let round = TspBuilder::new()
+        .rounding_policy(RoundingPolicy::Round)
+        .read_str(TEST_STR)
+        .unwrap();
+
+    for (a, b) in [(1, 2), (1, 3), (2, 3)] {
+        let w = tsp.weight(a, b);
+        assert_relative_eq!(w.trunc(), tsp.rounded_weight(a, b));
+        assert_relative_eq!(w.round(), round.rounded_weight(a, b));
+    }
+    assert_relative_eq!(509., tsp.rounded_weight(1, 2));
+    assert_relative_eq!(510., round.rounded_weight(1, 2));
+}
+
+#[test]
+fn test_has_negative_weights() {
+    let s = prep_weight!(WeightFormat::UpperRow.tsp_str(), "1 2 3 4 5 6 7 8 9 10");
+    assert!(!TspBuilder::parse_str(&s).unwrap().has_negative_weights());
+    let tsp = TspBuilder::parse_str(s.replace(" 7 ", " -7 ")).unwrap();
+    assert!(tsp.has_negative_weights());
+    assert!(!TspBuilder::parse_str(TEST_STR)
+        .unwrap()
+        .has_negative_weights());
+}
+
+#[test]
+fn test_two_opt_delta() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 4
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    2 10 0
+    3 0 10
+    4 10 10
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    // The tour 1-2-3-4 crosses itself. Reversing the segment 3-4 removes the crossing.
+    let delta = tsp.two_opt_delta(2, 3, 4, 1);
+    assert_relative_eq!(-8., delta);
+    let rounded_length = |t: &[usize]| -> f64 {
+        tsp.tour_edges(t)
+            .map(|(a, b)| tsp.rounded_weight(a, b))
+            .sum()
+    };
+    assert_relative_eq!(
+        rounded_length(&[1, 2, 3, 4]) + delta,
+        rounded_length(&[1, 2, 4, 3])
+    );
+    assert_relative_eq!(8., tsp.two_opt_delta(2, 4, 3, 1));
+
+    let mut tour = vec![1, 2, 3, 4];
+    Tsp::apply_two_opt(&mut tour, 2, 3).unwrap();
+    assert_eq!(vec![1, 2, 4, 3], tour);
+    assert_relative_eq!(rounded_length(&[1, 2, 3, 4]) + delta, rounded_length(&tour));
+    Tsp::apply_two_opt(&mut tour, 1, 1).unwrap();
+    assert_eq!(vec![1, 2, 4, 3], tour);
+    assert!(Tsp::apply_two_opt(&mut tour, 2, 1).is_err());
+    assert!(Tsp::apply_two_opt(&mut tour, 2, 4).is_err());
+    assert_eq!(vec![1, 2, 4, 3], tour);
+}
+
+#[test]
+fn test_coord_dimension() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 2
+    EDGE_WEIGHT_TYPE: EUC_3D
+    NODE_COORD_SECTION
+    1 0 0 0
+    2 1 2 3
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(Some(3), tsp.coord_dimension());
+
+    let s = prep_matrix!("TSP", 2, "FULL_MATRIX", "0 1\n1 0");
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(None, tsp.coord_dimension());
+}
+
+#[test]
+fn test_write_geojson() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 3
+    EDGE_WEIGHT_TYPE: GEO
+    NODE_COORD_SECTION
+    1 38.24 20.42
+    2 39.57 26.15
+    3 -40.56 -25.32
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    let mut buf = Vec::new();
+    tsp.write_geojson(&mut buf).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+    assert_eq!("FeatureCollection", json["type"]);
+    let features = json["features"].as_array().unwrap();
+    assert_eq!(tsp.dim(), features.len());
+    assert_eq!(1, features[0]["properties"]["id"]);
+    let coords = features[0]["geometry"]["coordinates"].as_array().unwrap();
+    assert_relative_eq!(20.7, coords[0].as_f64().unwrap(), epsilon = 1e-9);
+    assert_relative_eq!(38.4, coords[1].as_f64().unwrap(), epsilon = 1e-9);
+
+    let tsp = TspBuilder::parse_str(s.replace("GEO", "EUC_2D")).unwrap();
+    assert!(tsp.write_geojson(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn test_write_coords_ndjson() {
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    let mut buf = Vec::new();
+    tsp.write_coords_ndjson(&mut buf).unwrap();
+    let s = String::from_utf8(buf).unwrap();
+    let lines: Vec<&str> = s.lines().collect();
+    assert_eq!(tsp.dim(), lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        let json: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(i + 1, json["id"].as_u64().unwrap() as usize);
+        let pos = tsp.node_coords().get(&(i + 1)).unwrap().pos();
+        assert_eq!(pos[0], json["x"].as_f64().unwrap());
+        assert_eq!(pos[1], json["y"].as_f64().unwrap());
+    }
+}
+
+#[test]
+fn test_write_dot() {
+    let tsp = TspBuilder::parse_str(HCP_STR).unwrap();
+    let mut buf = Vec::new();
+    tsp.write_dot(&mut buf).unwrap();
+    let s = String::from_utf8(buf).unwrap();
+    let lines: Vec<&str> = s.lines().map(|l| l.trim()).collect();
+    assert_eq!("graph \"test\" {", lines[0]);
+    assert_eq!("}", lines[lines.len() - 1]);
+    for id in 1..=5 {
+        assert!(lines.contains(&format!("{};", id).as_str()));
+    }
+    for edge in [
+        "1 -- 2;", "2 -- 3;", "3 -- 4;", "4 -- 5;", "5 -- 1;", "1 -- 3;",
+    ] {
+        assert!(lines.contains(&edge), "{}", edge);
+    }
+    assert_eq!(1 + 5 + 6 + 1, lines.len());
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    let result = tsp.write_dot(&mut Vec::new());
+    assert!(matches!(result, Err(ParseTspError::InvalidInput { .. })));
+}
+
+#[test]
+fn test_coordinate_range() {
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert_eq!(Some((38.24, 40.56)), tsp.coordinate_range(0));
+    assert_eq!(Some((20.42, 26.15)), tsp.coordinate_range(1));
+    assert_eq!(None, tsp.coordinate_range(2));
+
+    let tsp = TspBuilder::parse_str(prep_weight!("UPPER_ROW", "1 2 3 4 5 6 7 8 9 10")).unwrap();
+    assert_eq!(None, tsp.coordinate_range(0));
+}
+
+#[test]
+fn test_display_coords() {
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert!(tsp.disp_coords().is_empty());
+    let pts = tsp.display_coords();
+    assert_eq!(
+        vec![1, 2, 3],
+        pts.iter().map(|p| p.id()).collect::<Vec<_>>()
+    );
+    assert_eq!(&vec![39.57, 26.15], pts[1].pos());
+
+    let tsp = TspBuilder::parse_str(TEST_STR.replace("COORD_DISPLAY", "NO_DISPLAY")).unwrap();
+    assert!(tsp.display_coords().is_empty());
+}
+
+#[test]
+fn test_scale() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 2
+    EDGE_WEIGHT_TYPE: EUC_2D
+    SCALE: 1000
+    NODE_COORD_SECTION
+    1 0 0
+    2 0.003 0.004
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(Some(1000.), tsp.scale_factor());
+    assert_eq!(
+        &vec![0.003, 0.004],
+        tsp.node_coords().get(&2).unwrap().pos()
+    );
+
+    let tsp = TspBuilder::new().apply_scale(true).read_str(s).unwrap();
+    assert_eq!(Some(1000.), tsp.scale_factor());
+    assert_relative_eq!(5., tsp.weight(1, 2), epsilon = 1e-9);
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert_eq!(None, tsp.scale_factor());
+}
+
+#[test]
+fn test_weight_row() {
+    let s = prep_matrix!("TSP", 3, "UPPER_ROW", "1 2\n3");
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    let row = tsp.weight_row(1);
+    assert_eq!(3, row.len());
+    for (j, w) in row.iter().enumerate() {
+        assert_relative_eq!(tsp.weight(1, j), *w);
+    }
+
+    let s = prep_matrix!("TSP", 3, "FULL_MATRIX", "0 1 2\n1 0 3\n2 3 0");
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(vec![1., 0., 3.], tsp.weight_row(1));
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    let row = tsp.weight_row(2);
+    assert_eq!(tsp.dim(), row.len());
+    for (j, w) in row.iter().enumerate() {
+        assert_relative_eq!(tsp.weight(2, j + 1), *w);
+    }
+}
+
+#[test]
+fn test_comment_in_section() {
+    let s = "
+    NAME: test
+    TYPE: CVRP
+    DIMENSION: 3
+    CAPACITY: 10
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    COMMENT: moved node 2
+    2 3 4
+    3 6 8
+    DEMAND_SECTION
+    1 0
+    2 1
+    COMMENT: demand of node 3 is estimated
+    3 2
+    EOF
+    ";
+
+    let (tsp, warnings) = TspBuilder::new()
+        .load_str(s)
+        .and_then(|b| b.build_with_warnings())
+        .unwrap();
+    assert_eq!(3, tsp.node_coords().len());
+    assert_eq!(&vec![3., 4.], tsp.node_coords().get(&2).unwrap().pos());
+    assert_eq!(3, tsp.demands().len());
+    assert_eq!(2, warnings.len());
+    assert!(warnings[0].contains("moved node 2"));
+}
+
+#[test]
+fn test_geometric_tour_length() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 3
+    EDGE_WEIGHT_TYPE: EXPLICIT
+    EDGE_WEIGHT_FORMAT: FULL_MATRIX
+    NODE_COORD_TYPE: TWOD_COORDS
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 0
+    3 3 4
+    EDGE_WEIGHT_SECTION
+    0 1 1
+    1 0 1
+    1 1 0
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_relative_eq!(3., tsp.tour_length(&[0, 1, 2]));
+    assert_relative_eq!(12., tsp.geometric_tour_length(&[1, 2, 3]).unwrap());
+    assert!(tsp.geometric_tour_length(&[1, 2, 4]).is_none());
+
+    let tsp = TspBuilder::parse_str(TEST_STR.replace("GEO", "EUC_2D")).unwrap();
+    assert_relative_eq!(
+        tsp.tour_length(&[1, 2, 3]),
+        tsp.geometric_tour_length(&[1, 2, 3]).unwrap()
+    );
+}
+
+#[test]
+fn test_fixed_edges() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 3
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 0
+    3 3 4
+    FIXED_EDGES_SECTION
+    1 2
+    2 3
+    -1
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(2, tsp.fixed_edge_count());
+    assert_eq!(&vec![(1, 2), (2, 3)], tsp.fixed_edges());
+
+    let result = TspBuilder::parse_str(s.replace("2 3\n    -1", "2 4\n    -1"));
+    match result {
+        Err(ParseTspError::InvalidInput { key, val }) => {
+            assert_eq!("FIXED_EDGES_SECTION", key);
+            assert_eq!("2 4", val);
+        }
+        _ => panic!("expected an invalid fixed edge"),
+    }
+}
+
+#[test]
+fn test_default_weight_kind() {
+    let s = prep_coords!("TYPE: TSP", "").replace("EDGE_WEIGHT_TYPE: EUC_2D", "");
+
+    assert!(matches!(
+        TspBuilder::parse_str(&s),
+        Err(ParseTspError::MissingEntry(_))
+    ));
+
+    let (tsp, warnings) = TspBuilder::new()
+        .default_weight_kind(WeightKind::Euc2d)
+        .load_str(s)
+        .and_then(|b| b.build_with_warnings())
+        .unwrap();
+    assert_eq!(WeightKind::Euc2d, tsp.weight_kind());
+    assert_relative_eq!(5., tsp.weight(1, 2));
+    assert_eq!(1, warnings.len());
+
+    let tsp = TspBuilder::new()
+        .default_weight_kind(WeightKind::Euc2d)
+        .read_str(TEST_STR)
+        .unwrap();
+    assert_eq!(WeightKind::Geo, tsp.weight_kind());
+}
+
+#[test]
+fn test_matrix_indexing() {
+    let s = prep_matrix!("ATSP", 3, "FULL_MATRIX", "0 1 2\n3 0 4\n5 6 0");
+
+    let tsp = TspBuilder::parse_str(&s).unwrap();
+    assert_eq!(MatrixIndexing::ZeroBased, tsp.matrix_indexing());
+    assert_relative_eq!(1., tsp.weight(0, 1));
+    assert_relative_eq!(6., tsp.weight(2, 1));
+    assert_relative_eq!(10., tsp.tour_length(&[0, 1, 2]));
+    assert_eq!(vec![5., 6., 0.], tsp.weight_row(2));
+    // Ids without a row of the matrix have no weight.
+    assert_relative_eq!(0., tsp.weight(2, 3));
+
+    let tsp = TspBuilder::new()
+        .matrix_indexing(MatrixIndexing::OneBased)
+        .read_str(&s)
+        .unwrap();
+    assert_eq!(MatrixIndexing::OneBased, tsp.matrix_indexing());
+    assert_relative_eq!(1., tsp.weight(1, 2));
+    assert_relative_eq!(6., tsp.weight(3, 2));
+    assert_relative_eq!(10., tsp.tour_length(&[1, 2, 3]));
+    assert_eq!(vec![5., 6., 0.], tsp.weight_row(3));
+    assert_eq!(Some(2), tsp.nearest_neighbor(1, &HashSet::new()));
+    assert_relative_eq!(0., tsp.weight(0, 1));
+    assert_relative_eq!(0., tsp.weight(1, 4));
+
+    // Tours of TSPLIB files refer to the rows of the matrix from 1.
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 3
+    EDGE_WEIGHT_TYPE: EXPLICIT
+    EDGE_WEIGHT_FORMAT: UPPER_ROW
+    EDGE_WEIGHT_SECTION
+    1 2
+    4
+    TOUR_SECTION
+    1 2 3 -1
+    EOF
+    ";
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(1, tsp.tour_lengths().len());
+    let tsp = TspBuilder::new()
+        .matrix_indexing(MatrixIndexing::OneBased)
+        .read_str(s)
+        .unwrap();
+    assert_eq!(vec![7.], tsp.tour_lengths());
+    assert!(tsp.check_consistency().is_empty());
+}
+
+#[test]
+fn test_relabel() {
+    let s = "
+    NAME: test
+    TYPE: CVRP
+    DIMENSION: 4
+    CAPACITY: 10
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 0
+    3 3 4
+    4 0 7
+    DEMAND_SECTION
+    1 0
+    2 1
+    3 2
+    4 3
+    DEPOT_SECTION
+    1
+    -1
+    EOF
+    ";
+
+    let mut tsp = TspBuilder::parse_str(s).unwrap();
+    let tour = vec![1, 3, 2, 4];
+    let len = tsp.tour_length(&tour);
+
+    let perm = [3, 1, 4, 2];
+    tsp.relabel(&perm).unwrap();
+    let relabeled: Vec<usize> = tour.iter().map(|&id| perm[id - 1]).collect();
+    assert_relative_eq!(len, tsp.tour_length(&relabeled));
+    assert_eq!(&vec![0., 0.], tsp.node_coords().get(&3).unwrap().pos());
+    assert!(tsp.depots().contains(&3));
+    assert_relative_eq!(3., *tsp.demands().get(&2).unwrap());
+
+    assert!(tsp.relabel(&[1, 2, 3]).is_err());
+    assert!(tsp.relabel(&[1, 2, 3, 3]).is_err());
+
+    let s = prep_matrix!("TSP", 4, "UPPER_ROW", "1 2 3\n4 5\n6");
+
+    let mut tsp = TspBuilder::parse_str(s).unwrap();
+    let tour = vec![0, 2, 1, 3];
+    let len = tsp.tour_length(&tour);
+    let perm = [2, 0, 3, 1];
+    tsp.relabel(&perm).unwrap();
+    let relabeled: Vec<usize> = tour.iter().map(|&id| perm[id]).collect();
+    assert_relative_eq!(len, tsp.tour_length(&relabeled));
+    assert_relative_eq!(1., tsp.weight(2, 0));
+
+    // Nodes of sparse graphs are numbered from 1 regardless of the matrix indexing.
+    let mut tsp = TspBuilder::parse_str(HCP_STR).unwrap();
+    assert!(tsp.relabel(&[4, 0, 1, 2, 3]).is_err());
+    tsp.relabel(&[5, 1, 2, 3, 4]).unwrap();
+    let edges: Vec<(usize, usize)> = tsp.edge_list().iter().map(|&(a, b, _)| (a, b)).collect();
+    assert_eq!(vec![(5, 1), (1, 2), (2, 3), (3, 4), (4, 5), (5, 2)], edges);
+
+    let mut tsp = TspBuilder::parse_str(HCP_STR.replace("1 3\n", "1 6\n")).unwrap();
+    assert!(tsp.relabel(&[5, 1, 2, 3, 4]).is_err());
+    assert_eq!(Some(&(1, 6, 0.)), tsp.edge_list().last());
+}
+
+#[test]
+fn test_edge_weight_misaligned() {
+    let s = prep_matrix!("TSP", 3, "UPPER_ROW", "1\t2\n3\t4");
+
+    assert!(TspBuilder::parse_str(&s).is_err());
+    assert!(TspBuilder::parse_str(s.replace("3\t4", "3")).is_ok());
+    assert!(TspBuilder::parse_str(s.replace("3\t4", "x")).is_err());
+}
+
+#[test]
+fn test_try_set_weight_kind() {
+    let mut tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    let w = tsp.weight(1, 2);
+    assert!(tsp.try_set_weight_kind(WeightKind::Euc3d).is_err());
+    assert!(tsp.try_set_weight_kind(WeightKind::Explicit).is_err());
+    assert!(tsp.try_set_weight_kind(WeightKind::Undefined).is_err());
+    assert_eq!(WeightKind::Geo, tsp.weight_kind());
+    assert_relative_eq!(w, tsp.weight(1, 2));
+
+    tsp.try_set_weight_kind(WeightKind::Euc2d).unwrap();
+    assert_eq!(WeightKind::Euc2d, tsp.weight_kind());
+    assert_relative_eq!((1.33_f64).hypot(5.73), tsp.weight(1, 2), epsilon = 1e-9);
+
+    let s = prep_weight!(WeightFormat::UpperRow.tsp_str(), "1 2 3 4 5 6 7 8 9 10");
+    let mut tsp = TspBuilder::parse_str(s).unwrap();
+    assert!(tsp.try_set_weight_kind(WeightKind::Euc2d).is_err());
+    assert!(tsp.try_set_weight_kind(WeightKind::Explicit).is_ok());
+}
+
+#[test]
+fn test_edge_weight_short_row() {
+    let s = prep_matrix!("ATSP", 3, "FULL_MATRIX", "0 1 2\n3 0\n6 7 0");
+
+    match TspBuilder::parse_str(&s) {
+        Err(ParseTspError::InvalidInput { val, .. }) => {
+            assert_eq!("row 1 has 2 values, expected 3", val)
+        }
+        r => panic!("unexpected result: {:?}", r),
+    }
+
+    // Without a row structure, only the total count is reported.
+    match TspBuilder::parse_str(s.replace("6 7 0", "6\n7 0")) {
+        Err(ParseTspError::InvalidInput { val, .. }) => {
+            assert_eq!("expected 9 values, found 8", val)
+        }
+        r => panic!("unexpected result: {:?}", r),
+    }
+
+    assert!(TspBuilder::parse_str(s.replace("3 0", "3 0 5")).is_ok());
+}
+
+#[test]
+fn test_setters() {
+    let mut tsp = TspBuilder::new()
+        .preserve_raw(true)
+        .read_str(TEST_STR)
+        .unwrap();
+    tsp.set_name("renamed");
+    tsp.set_comment("Edited");
+    tsp.set_kind(TspKind::Atsp);
+    assert_eq!("renamed", tsp.name());
+    assert_eq!(TspKind::Atsp, tsp.kind());
+
+    let mut buf = Vec::new();
+    tsp.write_tsplib(&mut buf).unwrap();
+    let s = String::from_utf8(buf).unwrap();
+    assert!(s.contains("NAME: renamed"));
+    assert!(s.contains("COMMENT: Edited"));
+    assert!(s.contains("TYPE: ATSP"));
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!("renamed", tsp.name());
+}
+
+#[test]
+fn test_eof_token() {
+    let s = "NAME: test
+    TYPE: TSP
+    DIMENSION: 2
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 4
+    EOFOO
+    EOF";
+
+    // A line merely starting with `EOF` is not the end of the input.
+    match TspBuilder::parse_str(s) {
+        Err(ParseTspError::InvalidEntry(e)) => assert_eq!("EOFOO", e),
+        _ => panic!("expected an invalid entry"),
+    }
+
+    let tsp = TspBuilder::parse_str(s.replace("    EOFOO\n", "")).unwrap();
+    assert_eq!(2, tsp.node_coords().len());
+
+    let s = s
+        .replace("    EOFOO\n", "")
+        .replace("EOF", "EOF trailing content");
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(2, tsp.node_coords().len());
+}
+
+#[test]
+fn test_trailer() {
+    let s = "NAME: test
+TYPE: TSP
+DIMENSION: 2
+EDGE_WEIGHT_TYPE: EUC_2D
+NODE_COORD_SECTION
+1 0 0
+2 3 4
+EOF
+BEST_KNOWN: 10
+SOLVER: none
+
+";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert!(tsp.trailer().is_empty());
+
+    let tsp = TspBuilder::new().capture_trailer(true).read_str(s).unwrap();
+    assert_eq!("BEST_KNOWN: 10\nSOLVER: none", tsp.trailer());
+
+    let tsp = TspBuilder::new()
+        .capture_trailer(true)
+        .preserve_raw(true)
+        .read_str(s)
+        .unwrap();
+    assert_eq!("BEST_KNOWN: 10\nSOLVER: none", tsp.trailer());
+
+    let tsp = TspBuilder::new()
+        .capture_trailer(true)
+        .read_str(TEST_STR)
+        .unwrap();
+    assert!(tsp.trailer().is_empty());
+}
+
+#[test]
+fn test_gtsp_sets() {
+    let s = "
+    NAME: test
+    TYPE: GTSP
+    DIMENSION: 5
+    GTSP_SETS: 3
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    2 1 0
+    3 2 0
+    4 3 0
+    5 4 0
+    GTSP_SET_SECTION:
+    2 3 4 -1
+    1 1
+    2 -1
+    3 5 -1
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(TspKind::Gtsp, tsp.kind());
+    assert_eq!(&vec![vec![1, 2], vec![3, 4], vec![5]], tsp.clusters());
+    assert!(tsp.present_sections().clusters);
+
+    let (tsp, warnings) = TspBuilder::new()
+        .load_str(s.replace("GTSP_SETS: 3", "GTSP_SETS: 4"))
+        .and_then(|b| b.build_with_warnings())
+        .unwrap();
+    assert_eq!(3, tsp.clusters().len());
+    assert_eq!(1, warnings.len());
+
+    match TspBuilder::parse_str(s.replace("3 5 -1", "3 5x -1")) {
+        Err(ParseTspError::InvalidInput { key, val }) => {
+            assert_eq!("GTSP_SET_SECTION", key);
+            assert_eq!("5x", val);
+        }
+        _ => panic!("expected an invalid node id"),
+    }
+}
+
+#[test]
+fn test_normalized_weight() {
+    let s = prep_matrix!("TSP", 3, "UPPER_ROW", "2 4\n6");
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!((2., 6.), tsp.weight_bounds());
+    assert_relative_eq!(0., tsp.normalized_weight(0, 1));
+    assert_relative_eq!(0.5, tsp.normalized_weight(0, 2));
+    assert_relative_eq!(1., tsp.normalized_weight(2, 1));
+
+    let tsp = TspBuilder::parse_str(prep_matrix!("TSP", 3, "UPPER_ROW", "3 3\n3")).unwrap();
+    assert_eq!((3., 3.), tsp.weight_bounds());
+    assert_relative_eq!(0., tsp.normalized_weight(0, 1));
+
+    let mut tsp = TspBuilder::parse_str(TEST_STR.replace("GEO", "EUC_2D")).unwrap();
+    let (_, max) = tsp.weight_bounds();
+    tsp.map_coords(|p| p.iter().map(|x| 2. * x).collect());
+    assert_relative_eq!(2. * max, tsp.weight_bounds().1, epsilon = 1e-9);
+}
+
+#[test]
+fn test_scientific_notation() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 3
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0.0e0 0
+    2 3.0E0 4e0
+    3 -6e-1 8.0e+1
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(&vec![-0.6, 80.], tsp.node_coords().get(&3).unwrap().pos());
+    assert_relative_eq!(5., tsp.weight(1, 2));
+
+    let s = "
+    NODE_COORD_SECTION
+    .3e1 4
+    -1.5e1 -2e1
+    EOF
+    ";
+
+    let tsp = TspBuilder::new()
+        .implicit_ids(true)
+        .read_str_headerless(s)
+        .unwrap();
+    assert_eq!(2, tsp.dim());
+    assert_relative_eq!(30., tsp.weight(1, 2));
+}
+
+#[test]
+fn test_explicit_with_coords() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 3
+    EDGE_WEIGHT_TYPE: EXPLICIT
+    EDGE_WEIGHT_FORMAT: UPPER_ROW
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 0
+    3 3 4
+    EDGE_WEIGHT_SECTION
+    7 8
+    9
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(CoordKind::Coord2d, tsp.coord_kind());
+    assert_eq!(3, tsp.node_coords().len());
+    assert_eq!(&vec![3., 4.], tsp.node_coords().get(&3).unwrap().pos());
+    assert_relative_eq!(7., tsp.weight(0, 1));
+    assert_relative_eq!(12., tsp.geometric_tour_length(&[1, 2, 3]).unwrap());
+
+    let tsp = TspBuilder::parse_str(
+        s.replace("3 3 4", "3 3 4 0")
+            .replace(" 0\n    2 3 0", " 0 0\n    2 3 0 0"),
+    )
+    .unwrap();
+    assert_eq!(CoordKind::Coord3d, tsp.coord_kind());
+
+    // Labels, blank lines and comments do not count as coordinates.
+    let tsp = TspBuilder::parse_str(s.replace("1 0 0", "1 0 0 Berlin")).unwrap();
+    assert_eq!(CoordKind::Coord2d, tsp.coord_kind());
+    assert_eq!("Berlin", tsp.node_labels().get(&1).unwrap());
+    let tsp = TspBuilder::parse_str(
+        s.replace("1 0 0", "\n    COMMENT: first node\n    1 0 0 1")
+            .replace("2 3 0", "2 3 0 1")
+            .replace("3 3 4", "3 3 4 1"),
+    )
+    .unwrap();
+    assert_eq!(CoordKind::Coord3d, tsp.coord_kind());
+    assert_eq!(3, tsp.node_coords().len());
+}
+
+#[test]
+fn test_tour_crossings() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 4
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    2 1 0
+    3 1 1
+    4 0 1
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(Some(0), tsp.tour_crossings(&[1, 2, 3, 4]));
+    assert_eq!(Some(1), tsp.tour_crossings(&[1, 3, 2, 4]));
+    assert_eq!(None, tsp.tour_crossings(&[1, 2, 5]));
+
+    let tsp = TspBuilder::parse_str(
+        s.replace("EUC_2D", "EUC_3D")
+            .replace(" 0\n", " 0 0\n")
+            .replace(" 1\n", " 1 0\n"),
+    )
+    .unwrap();
+    assert_eq!(None, tsp.tour_crossings(&[1, 2, 3, 4]));
+}
+
+#[test]
+fn test_pickup_delivery() {
+    let s = "
+    NAME: test
+    TYPE: CVRP
+    DIMENSION: 3
+    CAPACITY: 10
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 0
+    3 3 4
+    DEMAND_SECTION
+    1 0 0
+    2 4 0
+    3 0 4
+    DEPOT_SECTION
+    1
+    -1
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert!(tsp.demands().is_empty());
+    assert_eq!(3, tsp.pickup_delivery().len());
+    assert_eq!(Some(&(4., 0.)), tsp.pickup_delivery().get(&2));
+    assert_eq!(Some(&(0., 4.)), tsp.pickup_delivery().get(&3));
+
+    let tsp = TspBuilder::parse_str(s.replace("2 4 0", "2 4").replace("3 0 4", "3 5")).unwrap();
+    assert_eq!(2, tsp.demands().len());
+    assert_eq!(1, tsp.pickup_delivery().len());
+    assert_relative_eq!(5., *tsp.demands().get(&3).unwrap());
+}
+
+#[test]
+fn test_spec_key_order() {
+    let s = "
+    TYPE: TSP
+    NAME: test
+    EDGE_WEIGHT_TYPE: EUC_2D
+    DIMENSION: 2
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 4
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(
+        &vec!["TYPE", "NAME", "EDGE_WEIGHT_TYPE", "DIMENSION"],
+        tsp.spec_key_order()
+    );
+
+    let mut buf = Vec::new();
+    tsp.write_tsplib(&mut buf).unwrap();
+    let out = String::from_utf8(buf).unwrap();
+    let keys: Vec<&str> = out
+        .lines()
+        .take(4)
+        .map(|l| l.split(':').next().unwrap())
+        .collect();
+    assert_eq!(vec!["TYPE", "NAME", "EDGE_WEIGHT_TYPE", "DIMENSION"], keys);
+}
+
+#[test]
+fn test_one_tree_bound() {
+    let s = prep_matrix!("TSP", 5, "UPPER_ROW", "2 9 4 6\n3 7 8\n5 1\n2");
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    // MST on {1, 2, 3, 4}: (2, 4), (3, 4), (1, 2) with 1 + 2 + 3 = 6.
+    // Two cheapest edges at 0: (0, 1) and (0, 3) with 2 + 4 = 6.
+    assert_relative_eq!(12., tsp.one_tree_bound(0));
+    // MST on {0, 1, 2, 3}: (0, 1), (1, 2), (0, 3) with 2 + 3 + 4 = 9.
+    // Two cheapest edges at 4: (2, 4) and (3, 4) with 1 + 2 = 3.
+    assert_relative_eq!(12., tsp.one_tree_bound(4));
+    assert!(tsp.one_tree_bound(0) <= tsp.tour_length(&[0, 1, 2, 4, 3]));
+}
+
+#[test]
+fn test_node_labels() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 3
+    EDGE_WEIGHT_TYPE: EUC_3D
+    NODE_COORD_SECTION
+    1 0 0 0 Berlin
+    2 3 4 0 New York
+    3 6 8 0
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!(2, tsp.node_labels().len());
+    assert_eq!("Berlin", tsp.node_labels().get(&1).unwrap());
+    assert_eq!("New York", tsp.node_labels().get(&2).unwrap());
+    assert_eq!(&vec![3., 4., 0.], tsp.node_coords().get(&2).unwrap().pos());
+    assert_relative_eq!(5., tsp.weight(1, 2));
+
+    let tsp = TspBuilder::parse_str(TEST_STR).unwrap();
+    assert!(tsp.node_labels().is_empty());
+}
+
+#[test]
+fn test_parse_number_error() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    DIMENSION: 2
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 4
+    FIXED_EDGES_SECTION
+    1 x
+    -1
+    EOF
+    ";
+
+    match TspBuilder::parse_str(s) {
+        Err(ParseTspError::InvalidInput { key, val }) => {
+            assert_eq!("FIXED_EDGES_SECTION", key);
+            assert_eq!("x", val);
+        }
+        _ => panic!("expected an invalid integer"),
+    }
+}
+
+#[test]
+fn test_value_on_next_line() {
+    let s = "
+    NAME
+    test
+    TYPE:
+    TSP
+    DIMENSION
+
+    2
+    COMMENT:
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    1 0 0
+    2 3 4
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    assert_eq!("test", tsp.name());
+    assert_eq!(TspKind::Tsp, tsp.kind());
+    assert_eq!(2, tsp.dim());
+    assert_eq!("", tsp.comment());
+    assert_relative_eq!(5., tsp.weight(1, 2));
+
+    // Unknown entries are reported instead of being taken as values.
+    for next in ["SERVICE_TIME: 10", "DEMAND_DIMENSION : 1", "VEHICLE_TYPE"] {
+        let result = TspBuilder::parse_str(s.replace("test", next));
+        assert!(
+            matches!(&result, Err(ParseTspError::InvalidEntry(e)) if e == next),
+            "{:?}",
+            result
+        );
+    }
+}
+
+#[test]
+fn test_write_lkh_problem() {
+    let s = "
+    NAME: test
+    TYPE: TSP
+    COMMENT: Not for LKH
+    DIMENSION: 3
+    EDGE_WEIGHT_TYPE: EXPLICIT
+    EDGE_WEIGHT_FORMAT: UPPER_ROW
+    EDGE_WEIGHT_SECTION
+    1.4 2.6
+    3
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    let mut buf = Vec::new();
+    tsp.write_lkh_problem(&mut buf).unwrap();
+    let out = String::from_utf8(buf).unwrap();
+    assert_eq!(
+        "NAME : test\nTYPE : TSP\nDIMENSION : 3\nEDGE_WEIGHT_TYPE : EXPLICIT\n\
+         EDGE_WEIGHT_FORMAT : FULL_MATRIX\nEDGE_WEIGHT_SECTION\n0 1 3\n1 0 3\n3 3 0\nEOF\n",
+        out
+    );
+
+    let s = "
+    NAME: test
+    TYPE: CVRP
+    DIMENSION: 3
+    CAPACITY: 10.0
+    EDGE_WEIGHT_TYPE: EUC_2D
+    NODE_COORD_SECTION
+    2 0 0
+    5 3 4
+    7 6 8.5
+    DEMAND_SECTION
+    2 0
+    5 1
+    7 2
+    DEPOT_SECTION
+    2
+    -1
+    EOF
+    ";
+
+    let tsp = TspBuilder::parse_str(s).unwrap();
+    let mut buf = Vec::new();
+    tsp.write_lkh_problem(&mut buf).unwrap();
+    let out = String::from_utf8(buf).unwrap();
+    assert!(out.contains("CAPACITY : 10\n"));
+    // LKH rounds the distances, not the coordinates, so fractional coordinates are kept.
+    assert!(out.contains("NODE_COORD_SECTION\n1 0 0\n2 3 4\n3 6 8.5\n"));
+    assert!(out.contains("DEMAND_SECTION\n1 0\n2 1\n3 2\nDEPOT_SECTION\n1\n-1\nEOF\n"));
+
+    let mut tsp = TspBuilder::parse_str(out).unwrap();
+    assert_eq!(3, tsp.node_coords().len());
+
+    tsp.node_coords_mut().clear();
+    match tsp.write_lkh_problem(&mut Vec::new()) {
+        Err(ParseTspError::MissingEntry(key)) => assert_eq!("NODE_COORD_SECTION", key),
+        other => panic!("unexpected result: {:?}", other.err()),
+    }
+}
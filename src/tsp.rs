@@ -1,31 +1,42 @@
 use std::{
+    borrow::Cow,
+    cell::OnceCell,
     collections::{HashMap, HashSet},
     convert::TryFrom,
     fmt::Display,
     fs::File,
     io::{BufRead, BufReader},
+    iter::Peekable,
     path::Path,
+    str::FromStr,
 };
 
-use getset::{CopyGetters, Getters, MutGetters};
+use getset::{CopyGetters, Getters};
 
-use crate::error::ParseTspError;
+use crate::{error::ParseTspError, metric};
 
 // (Some) keywords for data specification part.
-static K_NAME: &str = "NAME";
-static K_TYPE: &str = "TYPE";
-static K_DIM: &str = "DIMENSION";
-static K_CAP: &str = "CAPACITY";
-static K_WEIGHT_TYPE: &str = "EDGE_WEIGHT_TYPE";
-static K_WEIGHT_FORMAT: &str = "EDGE_WEIGHT_FORMAT";
-static K_EDGE_FORMAT: &str = "EDGE_DATA_FORMAT";
-static K_NODE_COORD_TYPE: &str = "NODE_COORD_TYPE";
-static K_DISP_TYPE: &str = "DISPLAY_DATA_TYPE";
+pub(crate) static K_NAME: &str = "NAME";
+pub(crate) static K_TYPE: &str = "TYPE";
+pub(crate) static K_COMMENT: &str = "COMMENT";
+pub(crate) static K_DIM: &str = "DIMENSION";
+pub(crate) static K_CAP: &str = "CAPACITY";
+pub(crate) static K_WEIGHT_TYPE: &str = "EDGE_WEIGHT_TYPE";
+pub(crate) static K_WEIGHT_FORMAT: &str = "EDGE_WEIGHT_FORMAT";
+pub(crate) static K_EDGE_FORMAT: &str = "EDGE_DATA_FORMAT";
+pub(crate) static K_NODE_COORD_TYPE: &str = "NODE_COORD_TYPE";
+pub(crate) static K_DISP_TYPE: &str = "DISPLAY_DATA_TYPE";
+pub(crate) static K_SCALE: &str = "SCALE";
+pub(crate) static K_GTSP_SETS: &str = "GTSP_SETS";
+pub(crate) static K_VEHICLES: &str = "VEHICLES";
 
 // (Some) keywords for the data part.
-static K_NODE_COORD_SEC: &str = "NODE_COORD_SECTION";
-static K_EDGE_WEIGHT_SEC: &str = "EDGE_WEIGHT_SECTION";
-static K_TOUR_SEC: &str = "TOUR_SECTION";
+pub(crate) static K_NODE_COORD_SEC: &str = "NODE_COORD_SECTION";
+pub(crate) static K_EDGE_WEIGHT_SEC: &str = "EDGE_WEIGHT_SECTION";
+pub(crate) static K_TOUR_SEC: &str = "TOUR_SECTION";
+pub(crate) static K_TIME_WINDOW_SEC: &str = "TIME_WINDOW_SECTION";
+pub(crate) static K_GTSP_SET_SEC: &str = "GTSP_SET_SECTION";
+pub(crate) static K_DISP_SEC: &str = "DISPLAY_DATA_SECTION";
 
 /// Represents a parsed TSP dataset.
 ///
@@ -42,33 +53,45 @@ static K_TOUR_SEC: &str = "TOUR_SECTION";
 /// - ```COMMENT``` (optional): comments of a dataset.
 /// - ```DIM``` (required): the dimension of a dataset.
 /// - ```CAPACITY``` (required if ```TYPE``` is [`TspKind::Cvrp`]): the truck capacity in Capacitated
-/// Vehicle Routing Problem (CVRP).
+///   Vehicle Routing Problem (CVRP).
 /// - ```EDGE_WEIGHT_TYPE``` (required): indicates how the edge weights (or distances) are calculated.
-/// Represented by the enum [`WeightKind`].
+///   Represented by the enum [`WeightKind`].
 /// - ```EDGE_WEIGHT_FORMAT``` (required if ```EDGE_WEIGHT_TYPE``` is [`WeightKind::Explicit`]):
-/// specifies how the edge weights are given in the file. Represented by the enum [`WeightFormat`].
+///   specifies how the edge weights are given in the file. Represented by the enum [`WeightFormat`].
 /// - ```EDGE_DATA_FORMAT``` (optional): specifies how the edges of a graph are given in the file,
-/// if the graph is not complete. Represented by the enum [`EdgeFormat`].
+///   if the graph is not complete. Represented by the enum [`EdgeFormat`].
 /// - ```NODE_COORD_TYPE``` (required if ```EDGE_WEIGHT_TYPE``` is not [`WeightKind::Explicit`]):
-/// specifies how the coordinate for each node is given in the file. Represented by the enum [`CoordKind`].
+///   specifies how the coordinate for each node is given in the file. Represented by the enum [`CoordKind`].
 /// - ```DISPLAY_DATA_TYPE``` (optional): spcifies how the coordinate for each node for display
-/// purpose is given in the file. Represented by the enum [`DisplayKind`].
+///   purpose is given in the file. Represented by the enum [`DisplayKind`].
+/// - ```SCALE``` (optional, not part of TSPLIB): a factor for the node coordinates. See
+///   [`TspBuilder::apply_scale`].
+/// - ```GTSP_SETS``` (optional, not part of TSPLIB): the number of clusters in
+///   ```GTSP_SET_SECTION```.
+/// - ```VEHICLES``` (optional, not part of TSPLIB): the number of vehicles of a
+///   [`TspKind::Cvrp`]. See [`Tsp::fleet_capacity`].
 ///
 /// The *data part* has the following entries:
 /// - ```NODE_COORD_SECTION``` (required if ```NODE_COORD_TYPE``` is not [`CoordKind::NoCoord`]):
-/// a list of node coordinates.
+///   a list of node coordinates.
 /// - ```DEPOT_SECTION``` (relevant for [`TspKind::Cvrp`]): a list of possible alternate nodes.
-/// - ```DEMAND_SECTION``` (relevant for [`TspKind::Cvrp`]): a list of demands for all nodes. Each
-/// entry is a tuple ```(usize, usize)```, in which the first number is a node's id and the second
-/// number represents the demand for that node. All depot nodes must be also included in this section
-/// and their demands are always ```0```.
+/// - ```DEMAND_SECTION``` (required if ```TYPE``` is [`TspKind::Cvrp`]): a list of demands for all nodes. Each
+///   entry is a tuple ```(usize, usize)```, in which the first number is a node's id and the second
+///   number represents the demand for that node. All depot nodes must be also included in this section
+///   and their demands are always ```0```. Rows of the form ```id pickup delivery```, as used by
+///   pickup-and-delivery variants, are stored separately in [`Tsp::pickup_delivery`].
 /// - ```EDGE_DATA_SECTION```: a list of edges.
 /// - ```FIXED_EDGES_SECTION``` (optional): a list of edges that must be included in solutions to the problem.
 /// - ```DISPLAY_DATA_SECTION``` (required if ```DISPLAY_DATA_TYPE``` is [`DisplayKind::Disp2d`]):
-/// a list of 2D node coordinates for display purpose.
+///   a list of 2D node coordinates for display purpose.
 /// - ```TOUR_SECTION```: a collection of tours. Each tour is a sequence of node ids.
 /// - ```EDGE_WEIGHT_SECTION```(optional if ```EDGE_WEIGHT_FORMAT``` is [`WeightFormat::Function`]):
-///  node coordinates in a matrix form as dictated in ```EDGE_WEIGHT_FORMAT```.
+///   node coordinates in a matrix form as dictated in ```EDGE_WEIGHT_FORMAT```.
+/// - ```TIME_WINDOW_SECTION``` (optional, not part of TSPLIB): a list of time windows. Each entry
+///   consists of a node's id, followed by the earliest and the latest time of service.
+/// - ```GTSP_SET_SECTION``` (relevant for [`TspKind::Gtsp`], not part of TSPLIB): a list of
+///   clusters. Each entry consists of a cluster's id, followed by the ids of its nodes and
+///   terminated by ```-1```.
 ///
 /// # Example
 ///
@@ -105,11 +128,12 @@ static K_TOUR_SEC: &str = "TOUR_SECTION";
 /// let result = TspBuilder::parse_path(path);
 /// assert!(result.is_ok());
 /// ```
-#[derive(Debug, CopyGetters, Getters, MutGetters)]
+#[derive(Clone, Debug, CopyGetters, Getters)]
 pub struct Tsp {
     /// Name of the dataset.
     ///
-    /// Maps to the entry ```NAME``` in the TSP format.
+    /// Maps to the entry ```NAME``` in the TSP format. If the entry is empty and the dataset is
+    /// parsed from a file, the name defaults to the file name without extension.
     #[getset(get = "pub")]
     name: String,
     /// Type specifier of the dataset.
@@ -132,6 +156,17 @@ pub struct Tsp {
     /// Maps to the entry ```CAPACITY``` in the TSP format.
     #[getset(get_copy = "pub")]
     capacity: f64,
+    /// The factor for node coordinates, if given.
+    ///
+    /// Maps to the entry ```SCALE```, which is not part of the original TSP format.
+    #[getset(get_copy = "pub")]
+    scale_factor: Option<f64>,
+    /// The number of vehicles, if given.
+    ///
+    /// Maps to the entry ```VEHICLES```, which is not part of the original TSP format but used by
+    /// many vehicle routing instances.
+    #[getset(get_copy = "pub")]
+    vehicles: Option<usize>,
     /// Specifier for how the edge weights are calculated.
     ///
     /// Maps to the entry ```EDGE_WEIGHT_TYPE``` in the TSP format.
@@ -160,85 +195,1686 @@ pub struct Tsp {
     /// Vector of node coordinates, if available.
     ///
     /// Maps to the entry ```NODE_COORD_SECTION``` in the TSP format.
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     node_coords: HashMap<usize, Point>,
+    /// Labels of nodes, e.g. city names, if available.
+    ///
+    /// Maps to any tokens following the coordinates of a node in ```NODE_COORD_SECTION```.
+    #[getset(get = "pub")]
+    node_labels: HashMap<usize, String>,
     /// Vector of depot nodes' id, if available.
     ///
     /// Maps to the entry ```DEPOT_SECTION``` in the TSP format.
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     depots: HashSet<usize>,
+    /// Depots in the order in which they appear in ```DEPOT_SECTION```.
+    ///
+    /// See [`Tsp::depots_ordered`].
+    depot_order: Vec<usize>,
     /// Vector of node demands, if available.
     ///
     /// Maps to the entry ```DEMAND_SECTION``` in the TSP format.
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     demands: HashMap<usize, f64>,
+    /// Pickup and delivery quantities ```(pickup, delivery)``` of nodes, if available.
+    ///
+    /// Maps to rows of the entry ```DEMAND_SECTION``` that contain two quantities.
+    #[getset(get = "pub")]
+    pickup_delivery: HashMap<usize, (f64, f64)>,
     /// Vector of edges that *must* appear in solutions to the problem.
     ///
     /// Maps to the entry ```FIXED_EDGES_SECTION``` in the TSP format.
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     fixed_edges: Vec<(usize, usize)>,
     /// A vector of 2D node coordinates for display purpose, if available.
     ///
     /// Maps to the entry ```DISPLAY_DATA_SECTION``` in the TSP format.
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     disp_coords: Vec<Point>,
     /// Edge weights in a matrix form as stated in ```EDGE_WEIGHT_FORMAT```, if available.
     ///
     /// Maps to the entry ```EDGE_WEIGHT_SECTION``` in the TSP format.
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     edge_weights: Vec<Vec<f64>>,
     /// A collection of tours (a sequence of nodes).
     ///
     /// Maps to the entry ```TOUR_SECTION``` in the TSP format.
-    #[getset(get = "pub", get_mut = "pub")]
+    #[getset(get = "pub")]
     tours: Vec<Vec<usize>>,
+    /// Time windows ```(earliest, latest)``` of nodes, if available.
+    ///
+    /// Maps to the entry ```TIME_WINDOW_SECTION```, which is not part of the original TSP format
+    /// but used by instances of vehicle routing problems with time windows.
+    #[getset(get = "pub")]
+    time_windows: HashMap<usize, (f64, f64)>,
+    /// Clusters of nodes for [`TspKind::Gtsp`], ordered by their ids.
+    ///
+    /// Maps to the entry ```GTSP_SET_SECTION```, which is not part of the original TSP format
+    /// but used by instances of generalized travelling salesman problems.
+    #[getset(get = "pub")]
+    clusters: Vec<Vec<usize>>,
+    /// Whether geographical coordinates are given as ```longitude latitude```.
+    ///
+    /// Set with [`TspBuilder::geo_lon_lat_order`].
+    #[getset(get_copy = "pub")]
+    geo_lon_lat: bool,
+    /// How node ids map to rows and columns of an explicit weight matrix.
+    ///
+    /// Set with [`TspBuilder::matrix_indexing`].
+    #[getset(get_copy = "pub")]
+    matrix_indexing: MatrixIndexing,
+    /// How distances of [`WeightKind::Geo`] are rounded by [`Tsp::rounded_weight`].
+    ///
+    /// Set with [`TspBuilder::rounding_policy`].
+    #[getset(get_copy = "pub")]
+    rounding_policy: RoundingPolicy,
+    /// The keywords of the specification part in the order in which they appear in the input.
+    ///
+    /// Used by [`Tsp::write_tsplib_with`] to reproduce the original order.
+    #[getset(get = "pub")]
+    spec_key_order: Vec<String>,
+    /// Which optional data sections were present in the input.
+    ///
+    /// Unlike checking the corresponding collections for emptiness, this distinguishes an empty
+    /// section from a missing one.
+    #[getset(get_copy = "pub")]
+    present_sections: SectionFlags,
+    /// The lines after ```EOF```, if requested with [`TspBuilder::capture_trailer`].
+    ///
+    /// Lines are separated by ```\n``` and trailing whitespace is removed. Empty if there are no
+    /// such lines or if they are not captured.
+    #[getset(get = "pub")]
+    trailer: String,
+    /// The original lines of the input, if requested with [`TspBuilder::preserve_raw`].
+    raw_lines: Option<Vec<String>>,
+    /// Cached result of [`Tsp::weight_bounds`].
+    weight_bounds: OnceCell<(f64, f64)>,
+}
+
+/// Implements mutable accessors for fields of [`Tsp`] that discard all data derived from the
+/// current state, since the caller may change the field.
+macro_rules! impl_mut_getters {
+    ($($name:ident => $field:ident: $ty:ty),* $(,)?) => {
+        impl Tsp {
+            $(
+                #[doc = concat!("Returns a mutable reference to [`Tsp::", stringify!($field), "`].")]
+                ///
+                /// The input preserved with [`TspBuilder::preserve_raw`] is discarded, so that
+                /// [`Tsp::write_tsplib`] writes the data including any changes.
+                pub fn $name(&mut self) -> &mut $ty {
+                    self.invalidate_cache();
+                    &mut self.$field
+                }
+            )*
+        }
+    };
+}
+
+impl_mut_getters! {
+    node_coords_mut => node_coords: HashMap<usize, Point>,
+    node_labels_mut => node_labels: HashMap<usize, String>,
+    depots_mut => depots: HashSet<usize>,
+    demands_mut => demands: HashMap<usize, f64>,
+    pickup_delivery_mut => pickup_delivery: HashMap<usize, (f64, f64)>,
+    fixed_edges_mut => fixed_edges: Vec<(usize, usize)>,
+    disp_coords_mut => disp_coords: Vec<Point>,
+    edge_weights_mut => edge_weights: Vec<Vec<f64>>,
+    tours_mut => tours: Vec<Vec<usize>>,
+    time_windows_mut => time_windows: HashMap<usize, (f64, f64)>,
+    clusters_mut => clusters: Vec<Vec<usize>>,
 }
 
 impl Tsp {
+    /// Returns the original lines of the input, if they were preserved during parsing.
+    pub(crate) fn raw_lines(&self) -> Option<&Vec<String>> {
+        self.raw_lines.as_ref()
+    }
+
+    /// Discards all data derived from the current state of the dataset, i.e. the preserved input
+    /// lines and cached weights.
+    ///
+    /// Every method that changes the dataset must call this function, so that no data derived
+    /// from the previous state is returned afterwards.
+    fn invalidate_cache(&mut self) {
+        self.raw_lines = None;
+        self.weight_bounds = OnceCell::new();
+    }
+
+    /// Sets the name of the dataset.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = String::from(name);
+        self.invalidate_cache();
+    }
+
+    /// Sets the comment of the dataset.
+    pub fn set_comment(&mut self, comment: &str) {
+        self.comment = String::from(comment);
+        self.invalidate_cache();
+    }
+
+    /// Sets the type of the dataset.
+    ///
+    /// The data part is not changed, so the new type should be compatible with it.
+    pub fn set_kind(&mut self, kind: TspKind) {
+        self.kind = kind;
+        self.invalidate_cache();
+    }
+
+    /// Sets the edge weight type of the dataset, e.g. to reinterpret the node coordinates.
+    ///
+    /// An error is returned if the new type is not compatible with the data, i.e. if it computes
+    /// distances from more coordinates than the nodes have, or if it is
+    /// [`WeightKind::Explicit`] without an explicit weight matrix. The type is not changed in
+    /// this case.
+    pub fn try_set_weight_kind(&mut self, kind: WeightKind) -> Result<(), ParseTspError> {
+        let min_coords = self.node_coords.values().map(|p| p.pos().len()).min();
+        let required = match kind {
+            WeightKind::Explicit => {
+                if self.edge_weights.is_empty() {
+                    None
+                } else {
+                    Some(0)
+                }
+            }
+            WeightKind::Xray1 | WeightKind::Xray2 => Some(3),
+            WeightKind::Custom => Some(0),
+            WeightKind::Undefined => None,
+            _ => match CoordKind::from(kind) {
+                CoordKind::Coord3d => Some(3),
+                _ => Some(2),
+            },
+        };
+
+        match (required, min_coords) {
+            (Some(0), _) => {}
+            (Some(n), Some(m)) if m >= n => {}
+            _ => {
+                return Err(ParseTspError::InvalidInput {
+                    key: String::from(K_WEIGHT_TYPE),
+                    val: String::from(kind.tsp_str()),
+                })
+            }
+        }
+
+        self.weight_kind = kind;
+        self.invalidate_cache();
+        Ok(())
+    }
+
+    /// Returns the edge weight type as written in TSPLIB, e.g. ```"EUC_2D"```.
+    ///
+    /// Unlike the [`Display`] implementation of [`WeightKind`], which prints the variant name,
+    /// the result can be parsed back with [`str::parse`].
+    pub fn weight_kind_str(&self) -> &'static str {
+        self.weight_kind.tsp_str()
+    }
+
     /// Returns the edge weight between two nodes.
     ///
+    /// If the weight type is [`WeightKind::Explicit`] but the weight format is
+    /// [`WeightFormat::Function`], no matrix is given in the file and the weights are undefined.
+    /// In this case, the function always returns ```0.```.
+    ///
     /// # Arguments
-    /// * a - index of the first node.
-    /// * b - index of the second node.
+    /// * a - id of the first node.
+    /// * b - id of the second node.
+    ///
+    /// For explicit edge weights, ids are mapped to the matrix according to
+    /// [`Tsp::matrix_indexing`]. With [`WeightFormat::FullMatrix`], ```weight(a, b)``` is read from
+    /// row ```a``` and column ```b```, so that asymmetric weights, e.g. of [`TspKind::Atsp`], are
+    /// preserved.
     pub fn weight(&self, a: usize, b: usize) -> f64 {
         match self.weight_kind {
-            WeightKind::Explicit => match self.weight_format {
-                WeightFormat::Function => 0.,
-                WeightFormat::FullMatrix => self.edge_weights[a][b],
-                WeightFormat::UpperRow | WeightFormat::LowerCol => match a.cmp(&b) {
-                    std::cmp::Ordering::Less => self.edge_weights[a][b - a - 1],
-                    std::cmp::Ordering::Equal => 0.,
-                    std::cmp::Ordering::Greater => self.edge_weights[b][a - b - 1],
-                },
-                WeightFormat::UpperDiagRow | WeightFormat::LowerDiagCol => {
-                    if a < b {
-                        self.edge_weights[a][b - a]
-                    } else {
-                        self.edge_weights[b][a - b]
+            WeightKind::Explicit => match (self.matrix_index(a), self.matrix_index(b)) {
+                (Some(ia), Some(ib)) => self.matrix_weight(ia, ib).unwrap_or(0.),
+                _ => 0.,
+            },
+            _ => {
+                if let (Some(na), Some(nb)) = (self.node_coords.get(&a), self.node_coords.get(&b)) {
+                    match self.weight_kind {
+                        // Most common case, which skips the dispatch in `WeightKind::cost`.
+                        WeightKind::Euc2d => metric::euc_2d(na.pos(), nb.pos()),
+                        WeightKind::Geo if self.geo_lon_lat => {
+                            metric::geo_lon_lat(na.pos(), nb.pos())
+                        }
+                        wk => wk.cost(na.pos(), nb.pos()),
                     }
+                } else {
+                    0.
+                }
+            }
+        }
+    }
+
+    /// Returns the row or column of the weight matrix for a node id, or ```None``` if the id is
+    /// not a node of the dataset.
+    fn matrix_index(&self, id: usize) -> Option<usize> {
+        let idx = match self.matrix_indexing {
+            MatrixIndexing::ZeroBased => Some(id),
+            MatrixIndexing::OneBased => id.checked_sub(1),
+        }?;
+        if idx < self.dim {
+            Some(idx)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the explicit edge weight between two 0-based matrix indices, or ```None``` if the
+    /// weight is not stored, e.g. on the diagonal of a matrix without diagonal entries.
+    fn matrix_weight(&self, a: usize, b: usize) -> Option<f64> {
+        let (r, c) = self.matrix_cell(a, b)?;
+        self.edge_weights.get(r)?.get(c).copied()
+    }
+
+    /// Returns the position in ```edge_weights``` that stores the weight between two 0-based
+    /// matrix indices, or ```None``` if the weight is not stored.
+    fn matrix_cell(&self, a: usize, b: usize) -> Option<(usize, usize)> {
+        match self.weight_format {
+            WeightFormat::Function => None,
+            WeightFormat::FullMatrix => Some((a, b)),
+            WeightFormat::UpperRow | WeightFormat::LowerCol => match a.cmp(&b) {
+                std::cmp::Ordering::Less => Some((a, b - a - 1)),
+                std::cmp::Ordering::Equal => None,
+                std::cmp::Ordering::Greater => Some((b, a - b - 1)),
+            },
+            WeightFormat::UpperDiagRow | WeightFormat::LowerDiagCol => {
+                if a < b {
+                    Some((a, b - a))
+                } else {
+                    Some((b, a - b))
+                }
+            }
+            WeightFormat::LowerRow | WeightFormat::UpperCol => match a.cmp(&b) {
+                std::cmp::Ordering::Less => Some((b - 1, a)),
+                std::cmp::Ordering::Equal => None,
+                std::cmp::Ordering::Greater => Some((a - 1, b)),
+            },
+            WeightFormat::LowerDiagRow | WeightFormat::UpperDiagCol => {
+                if a < b {
+                    Some((b, a))
+                } else {
+                    Some((a, b))
+                }
+            }
+            WeightFormat::Undefined => None,
+        }
+    }
+
+    /// Returns the edge weights from the node ```from``` to all nodes in ascending order of
+    /// node ids, including ```from``` itself.
+    ///
+    /// For a full matrix, the row is copied directly. Otherwise, this is equivalent to calling
+    /// [`Tsp::weight`] for each node.
+    pub fn weight_row(&self, from: usize) -> Vec<f64> {
+        if self.weight_kind == WeightKind::Explicit
+            && self.weight_format == WeightFormat::FullMatrix
+        {
+            if let Some(row) = self
+                .matrix_index(from)
+                .and_then(|idx| self.edge_weights.get(idx))
+            {
+                return row.clone();
+            }
+        }
+
+        self.node_ids()
+            .into_iter()
+            .map(|to| self.weight(from, to))
+            .collect()
+    }
+
+    /// Returns the number of edges in ```FIXED_EDGES_SECTION```.
+    pub fn fixed_edge_count(&self) -> usize {
+        self.fixed_edges.len()
+    }
+
+    /// Returns the number of coordinates of the parsed points.
+    ///
+    /// Unlike [`Tsp::coord_kind`], this is derived from the points themselves rather than the
+    /// header. Returns ```None``` if there are no points or if they differ in length.
+    pub fn coord_dimension(&self) -> Option<usize> {
+        let mut lens = self.node_coords.values().map(|p| p.pos().len());
+        let first = lens.next()?;
+        if lens.all(|l| l == first) {
+            Some(first)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the minimum and maximum of the coordinate with index ```dim_index``` over all
+    /// nodes, e.g. ```0``` for ```x``` and ```1``` for ```y```.
+    ///
+    /// Returns ```None``` if there are no node coordinates or if ```dim_index``` is not less than
+    /// [`Tsp::coord_dimension`].
+    pub fn coordinate_range(&self, dim_index: usize) -> Option<(f64, f64)> {
+        if dim_index >= self.coord_dimension()? {
+            return None;
+        }
+
+        self.node_coords
+            .values()
+            .map(|p| p.pos()[dim_index])
+            .fold(None, |range, x| match range {
+                None => Some((x, x)),
+                Some((lo, hi)) => Some((x.min(lo), x.max(hi))),
+            })
+    }
+
+    /// Returns the ids and two-dimensional node coordinates as parallel arrays
+    /// ```(ids, xs, ys)```, in the order of node ids.
+    ///
+    /// This structure-of-arrays layout is suited for vectorized computations. The arrays are
+    /// empty unless all nodes have two coordinates.
+    pub fn coords_soa(&self) -> (Vec<usize>, Vec<f64>, Vec<f64>) {
+        if self.coord_dimension() != Some(2) {
+            return (Vec::new(), Vec::new(), Vec::new());
+        }
+
+        let mut pts: Vec<&Point> = self.node_coords.values().collect();
+        pts.sort_by_key(|p| p.id());
+
+        let ids = pts.iter().map(|p| p.id()).collect();
+        let xs = pts.iter().map(|p| p.pos()[0]).collect();
+        let ys = pts.iter().map(|p| p.pos()[1]).collect();
+        (ids, xs, ys)
+    }
+
+    /// Returns the coordinates used to display the nodes, sorted by id.
+    ///
+    /// These are [`Tsp::disp_coords`] if available. Otherwise, if the display type is
+    /// [`DisplayKind::DispCoo`], they are derived from [`Tsp::node_coords`]. Returns an empty
+    /// vector if neither applies.
+    pub fn display_coords(&self) -> Vec<Point> {
+        let mut pts: Vec<Point> = if !self.disp_coords.is_empty() {
+            self.disp_coords.clone()
+        } else if self.disp_kind == DisplayKind::DispCoo {
+            self.node_coords.values().cloned().collect()
+        } else {
+            Vec::new()
+        };
+        pts.sort_by_key(|p| p.id());
+        pts
+    }
+
+    /// Returns the edge weight between two nodes, rounded to an integer according to the
+    /// conventions of TSPLIB.
+    ///
+    /// See [`Tsp::weight`] and [`WeightKind::round_weight`]. Distances of [`WeightKind::Geo`] are
+    /// rounded according to [`Tsp::rounding_policy`].
+    pub fn rounded_weight(&self, a: usize, b: usize) -> f64 {
+        let w = self.weight(a, b);
+        match (self.weight_kind, self.rounding_policy) {
+            (WeightKind::Geo, RoundingPolicy::Round) => w.round(),
+            _ => self.weight_kind.round_weight(w),
+        }
+    }
+
+    /// Returns the rounded edge weight between two nodes as ```i32```.
+    ///
+    /// See [`Tsp::rounded_weight`]. Weights out of range of ```i32``` saturate.
+    pub fn weight_i32(&self, a: usize, b: usize) -> i32 {
+        self.rounded_weight(a, b) as i32
+    }
+
+    /// Returns the rounded edge weight between two nodes as ```i64```.
+    ///
+    /// See [`Tsp::rounded_weight`]. Weights out of range of ```i64``` saturate.
+    pub fn weight_i64(&self, a: usize, b: usize) -> i64 {
+        self.rounded_weight(a, b) as i64
+    }
+
+    /// Returns the matrix of all edge weights, rounded according to the conventions of TSPLIB
+    /// and cast to ```u32```.
+    ///
+    /// Rows and columns are ordered by node ids, i.e. ```m[i][j]``` is the weight between the
+    /// ```i```-th and ```j```-th smallest node id. Negative weights saturate to ```0``` and weights
+    /// too large for ```u32``` to [`u32::MAX`].
+    pub fn weight_matrix_u32(&self) -> Vec<Vec<u32>> {
+        let ids = self.node_ids();
+        ids.iter()
+            .map(|&a| {
+                ids.iter()
+                    .map(|&b| {
+                        let w = self.rounded_weight(a, b);
+                        if w <= 0. {
+                            0
+                        } else if w >= u32::MAX as f64 {
+                            u32::MAX
+                        } else {
+                            w as u32
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the matrix of all edge weights, multiplied by ```scale``` and rounded to ```i64```.
+    ///
+    /// This preserves fractional weights up to the precision given by the scale factor for
+    /// solvers that require integer weights, e.g. OR-Tools. Rows and columns are ordered as in
+    /// [`Tsp::weight_matrix_u32`]. Weights out of range of ```i64``` saturate.
+    pub fn weight_matrix_scaled_i64(&self, scale: f64) -> Vec<Vec<i64>> {
+        let ids = self.node_ids();
+        ids.iter()
+            .map(|&a| {
+                ids.iter()
+                    .map(|&b| (self.weight(a, b) * scale).round() as i64)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the edge weights ```weight(i, j)``` for ```i < j``` in row-major order, as in the
+    /// TSPLIB format ```UPPER_ROW```.
+    ///
+    /// Nodes are ordered as in [`Tsp::weight_matrix_u32`]. The result has
+    /// ```dim * (dim - 1) / 2``` entries and is only meaningful for symmetric instances.
+    pub fn upper_triangular_weights(&self) -> Vec<f64> {
+        let ids = self.node_ids();
+        ids.iter()
+            .enumerate()
+            .flat_map(|(i, &a)| ids[i + 1..].iter().map(move |&b| self.weight(a, b)))
+            .collect()
+    }
+
+    /// Returns the ids of all nodes in ascending order, as accepted by [`Tsp::weight`].
+    ///
+    /// For datasets with node coordinates, these are the ids given in ```NODE_COORD_SECTION```.
+    /// For explicit edge weights, these are the ids of the matrix rows according to
+    /// [`Tsp::matrix_indexing`].
+    pub(crate) fn node_ids(&self) -> Vec<usize> {
+        if self.node_coords.is_empty() {
+            match self.matrix_indexing {
+                MatrixIndexing::ZeroBased => (0..self.dim).collect(),
+                MatrixIndexing::OneBased => (1..=self.dim).collect(),
+            }
+        } else {
+            let mut ids: Vec<usize> = self.node_coords.keys().copied().collect();
+            ids.sort_unstable();
+            ids
+        }
+    }
+
+    /// Returns the distance between a node and an arbitrary point under the metric of the dataset.
+    ///
+    /// Returns ```0.``` if the node has no coordinates, e.g. for explicit edge weights.
+    pub fn weight_to_coord(&self, id: usize, coord: &[f64]) -> f64 {
+        match (self.weight_kind, self.node_coords.get(&id)) {
+            (WeightKind::Explicit, _) | (_, None) => 0.,
+            (WeightKind::Geo, Some(pt)) if self.geo_lon_lat => metric::geo_lon_lat(pt.pos(), coord),
+            (wk, Some(pt)) => wk.cost(pt.pos(), coord),
+        }
+    }
+
+    /// Returns the node closest to an arbitrary point under the metric of the dataset.
+    ///
+    /// If several nodes have the same distance, the one with the smallest id is returned. Returns
+    /// ```None``` if the dataset has no node coordinates or explicit edge weights.
+    pub fn nearest_node_to(&self, coord: &[f64]) -> Option<usize> {
+        if self.weight_kind == WeightKind::Explicit {
+            return None;
+        }
+
+        let mut ids: Vec<usize> = self.node_coords.keys().copied().collect();
+        ids.sort_unstable();
+        ids.into_iter()
+            .map(|id| (id, self.weight_to_coord(id, coord)))
+            .fold(None, |best: Option<(usize, f64)>, (id, w)| match best {
+                Some((_, bw)) if w >= bw => best,
+                _ => Some((id, w)),
+            })
+            .map(|(id, _)| id)
+    }
+
+    /// Returns the node closest to the node ```from```, ignoring all nodes in ```excluded```.
+    ///
+    /// If several nodes have the same distance, the one with the smallest id is returned. Returns
+    /// ```None``` if there is no candidate left.
+    pub fn nearest_neighbor(&self, from: usize, excluded: &HashSet<usize>) -> Option<usize> {
+        self.scan_neighbors(from, |id| excluded.contains(&id), |w, best| w < best)
+    }
+
+    /// Returns the node furthest from the node ```from```, ignoring all nodes in ```excluded```.
+    ///
+    /// If several nodes have the same distance, the one with the smallest id is returned. Returns
+    /// ```None``` if there is no candidate left.
+    pub fn furthest_neighbor(&self, from: usize, excluded: &HashSet<usize>) -> Option<usize> {
+        self.scan_neighbors(from, |id| excluded.contains(&id), |w, best| w > best)
+    }
+
+    /// Returns all nodes except ```from``` together with their weights from ```from```, sorted in
+    /// ascending order of the weights.
+    ///
+    /// Nodes with the same weight are ordered by their ids. The ```k``` nearest neighbors are the
+    /// first ```k``` entries of the result.
+    pub fn sorted_neighbors(&self, from: usize) -> Vec<(usize, f64)> {
+        let mut v: Vec<(usize, f64)> = self
+            .node_ids()
+            .into_iter()
+            .filter(|&id| id != from)
+            .map(|id| (id, self.weight(from, id)))
+            .collect();
+        v.sort_by(|a, b| a.1.total_cmp(&b.1));
+        v
+    }
+
+    /// Returns a vector of flags for marking visited nodes during tour construction.
+    ///
+    /// The vector has the length ```dim + 1``` and is indexed directly by node ids, which start
+    /// at ```1``` in TSPLIB. All flags are initially ```false```. It is a cheaper alternative to a
+    /// [`HashSet`] for [`Tsp::nearest_unvisited`] and [`Tsp::furthest_unvisited`].
+    pub fn visited_bitset(&self) -> Vec<bool> {
+        vec![false; self.dim + 1]
+    }
+
+    /// Returns the node closest to the node ```from``` among all nodes not flagged in ```visited```.
+    ///
+    /// Behaves like [`Tsp::nearest_neighbor`], with the visited nodes given by the flags created
+    /// by [`Tsp::visited_bitset`]. Ids beyond the length of ```visited``` count as unvisited.
+    pub fn nearest_unvisited(&self, from: usize, visited: &[bool]) -> Option<usize> {
+        self.scan_neighbors(from, |id| is_flagged(visited, id), |w, best| w < best)
+    }
+
+    /// Returns the node furthest from the node ```from``` among all nodes not flagged in
+    /// ```visited```.
+    ///
+    /// Behaves like [`Tsp::furthest_neighbor`], with the visited nodes given by the flags created
+    /// by [`Tsp::visited_bitset`]. Ids beyond the length of ```visited``` count as unvisited.
+    pub fn furthest_unvisited(&self, from: usize, visited: &[bool]) -> Option<usize> {
+        self.scan_neighbors(from, |id| is_flagged(visited, id), |w, best| w > best)
+    }
+
+    /// Constructs a tour with the nearest neighbor heuristic, starting at the node ```start```.
+    ///
+    /// Starting from ```start```, the closest node not yet visited is appended until all nodes
+    /// are part of the tour. Ties are broken by the smallest id, see [`Tsp::nearest_unvisited`].
+    pub fn nearest_neighbor_tour(&self, start: usize) -> Vec<usize> {
+        let ids = self.node_ids();
+        let mut visited = vec![false; ids.iter().max().map_or(0, |&id| id + 1).max(start + 1)];
+        let mut tour = Vec::with_capacity(ids.len());
+
+        let mut current = Some(start);
+        while let Some(id) = current {
+            visited[id] = true;
+            tour.push(id);
+            current = self.nearest_unvisited(id, &visited);
+        }
+
+        tour
+    }
+
+    /// Scans all nodes except ```from``` and the excluded ones, and returns the node whose weight
+    /// is preferred over the weights of all other nodes.
+    fn scan_neighbors<E, P>(&self, from: usize, is_excluded: E, prefer: P) -> Option<usize>
+    where
+        E: Fn(usize) -> bool,
+        P: Fn(f64, f64) -> bool,
+    {
+        let mut best: Option<(usize, f64)> = None;
+
+        for id in self.node_ids() {
+            if id == from || is_excluded(id) {
+                continue;
+            }
+
+            let w = self.weight(from, id);
+            match best {
+                Some((_, bw)) if !prefer(w, bw) => {}
+                _ => best = Some((id, w)),
+            }
+        }
+
+        best.map(|(id, _)| id)
+    }
+
+    /// Returns the depots in the order in which they appear in ```DEPOT_SECTION```.
+    ///
+    /// Some multi-depot algorithms distinguish depots by this order, which is lost in
+    /// [`Tsp::depots`]. Depots added through [`Tsp::depots_mut`] follow in ascending order, and
+    /// removed depots are skipped.
+    pub fn depots_ordered(&self) -> Vec<usize> {
+        let mut depots: Vec<usize> = self
+            .depot_order
+            .iter()
+            .filter(|id| self.depots.contains(id))
+            .copied()
+            .collect();
+        let mut rest: Vec<usize> = self
+            .depots
+            .iter()
+            .filter(|id| !self.depot_order.contains(id))
+            .copied()
+            .collect();
+        rest.sort_unstable();
+        depots.append(&mut rest);
+        depots
+    }
+
+    /// Returns the sum of the demands of all nodes.
+    pub fn total_demand(&self) -> f64 {
+        self.demands.values().sum()
+    }
+
+    /// Returns the total capacity of the fleet, i.e. [`Tsp::capacity`] times [`Tsp::vehicles`].
+    ///
+    /// Returns ```None``` if the number of vehicles or the capacity is not given.
+    pub fn fleet_capacity(&self) -> Option<f64> {
+        match self.vehicles {
+            Some(n) if self.capacity > 0. => Some(self.capacity * n as f64),
+            _ => None,
+        }
+    }
+
+    /// Checks whether the fleet can serve the total demand, i.e. whether
+    /// [`Tsp::total_demand`] does not exceed [`Tsp::fleet_capacity`].
+    ///
+    /// This is a necessary condition for a feasible solution only, since demands cannot be split
+    /// between vehicles. Returns ```None``` if the fleet capacity is not known.
+    pub fn is_feasible_fleet(&self) -> Option<bool> {
+        self.fleet_capacity()
+            .map(|capacity| self.total_demand() <= capacity)
+    }
+
+    /// Returns the distances from each depot to all other nodes.
+    ///
+    /// The outer map is keyed by depot ids, and each inner map contains the weights from that
+    /// depot to every other node, keyed by node ids. The map is empty if no depot is given.
+    pub fn depot_distances(&self) -> HashMap<usize, HashMap<usize, f64>> {
+        let ids = self.node_ids();
+
+        self.depots
+            .iter()
+            .map(|&depot| {
+                let dists = ids
+                    .iter()
+                    .filter(|&&id| id != depot)
+                    .map(|&id| (id, self.weight(depot, id)))
+                    .collect();
+                (depot, dists)
+            })
+            .collect()
+    }
+
+    /// Returns the depot closest to a node.
+    ///
+    /// A depot is closest to itself. If several depots have the same distance, the one with the
+    /// smallest id is returned. Returns ```None``` if no depot is given.
+    pub fn nearest_depot(&self, node: usize) -> Option<usize> {
+        if self.depots.contains(&node) {
+            return Some(node);
+        }
+
+        let mut depots: Vec<usize> = self.depots.iter().copied().collect();
+        depots.sort_unstable();
+
+        let mut best: Option<(usize, f64)> = None;
+        for depot in depots {
+            let w = self.weight(node, depot);
+            match best {
+                Some((_, bw)) if w >= bw => {}
+                _ => best = Some((depot, w)),
+            }
+        }
+
+        best.map(|(id, _)| id)
+    }
+
+    /// Returns an iterator over the edges of a tour.
+    ///
+    /// Each consecutive pair ```(tour[i], tour[i + 1])``` is yielded, followed by the closing
+    /// edge ```(tour[last], tour[0])```. Hence, the number of edges equals the length of the tour.
+    pub fn tour_edges<'a>(&self, tour: &'a [usize]) -> impl Iterator<Item = (usize, usize)> + 'a {
+        tour.iter()
+            .zip(tour.iter().cycle().skip(1))
+            .map(|(a, b)| (*a, *b))
+    }
+
+    /// Returns the length of a closed tour, i.e. the sum of the weights of all edges including the
+    /// edge from the last node back to the first node.
+    pub fn tour_length(&self, tour: &[usize]) -> f64 {
+        self.tour_edges(tour).map(|(a, b)| self.weight(a, b)).sum()
+    }
+
+    /// Returns the change in tour length of a 2-opt move that replaces the edges ```(a, b)``` and
+    /// ```(c, d)``` with ```(a, c)``` and ```(b, d)```, i.e. reverses the segment from ```b``` to
+    /// ```c```.
+    ///
+    /// A negative value means that the move shortens the tour. Weights are rounded as in
+    /// [`Tsp::rounded_weight`].
+    pub fn two_opt_delta(&self, a: usize, b: usize, c: usize, d: usize) -> f64 {
+        self.rounded_weight(a, c) + self.rounded_weight(b, d)
+            - self.rounded_weight(a, b)
+            - self.rounded_weight(c, d)
+    }
+
+    /// Applies a 2-opt move to a tour by reversing the segment ```tour[i..=j]``` in place.
+    ///
+    /// With ```a = tour[i - 1]```, ```b = tour[i]```, ```c = tour[j]``` and ```d = tour[j + 1]```
+    /// (wrapping around at the ends), the length of the tour changes by
+    /// [`Tsp::two_opt_delta`]```(a, b, c, d)```. An error is returned and the tour is left
+    /// unchanged if ```i > j``` or ```j``` is out of bounds.
+    pub fn apply_two_opt(tour: &mut [usize], i: usize, j: usize) -> Result<(), ParseTspError> {
+        if i > j || j >= tour.len() {
+            return Err(ParseTspError::Other("invalid segment of tour"));
+        }
+
+        tour[i..=j].reverse();
+        Ok(())
+    }
+
+    /// Returns the length of a closed tour with exact and with rounded weights, as
+    /// ```(exact, rounded)```.
+    ///
+    /// Weights are rounded as in [`Tsp::rounded_weight`]. The difference shows how much the
+    /// integer weights of TSPLIB change the length of the tour.
+    pub fn rounding_report(&self, tour: &[usize]) -> (f64, f64) {
+        self.tour_edges(tour)
+            .fold((0., 0.), |(exact, rounded), (a, b)| {
+                (
+                    exact + self.weight(a, b),
+                    rounded + self.rounded_weight(a, b),
+                )
+            })
+    }
+
+    /// Returns the length of an open path, i.e. the sum of the weights of all edges between
+    /// consecutive nodes.
+    ///
+    /// Unlike [`Tsp::tour_length`], the edge from the last node back to the first node is not
+    /// included, which matches open-path problems such as SOP.
+    pub fn path_length(&self, path: &[usize]) -> f64 {
+        path.windows(2).map(|w| self.weight(w[0], w[1])).sum()
+    }
+
+    /// Returns the Euclidean length of a closed tour computed from the node coordinates,
+    /// regardless of the weight type of the dataset.
+    ///
+    /// This allows comparing explicit edge weights against the geometry of the nodes. Returns
+    /// ```None``` if a node of the tour has no coordinates.
+    pub fn geometric_tour_length(&self, tour: &[usize]) -> Option<f64> {
+        self.tour_edges(tour)
+            .map(|(a, b)| {
+                let (pa, pb) = (
+                    self.node_coords.get(&a)?.pos(),
+                    self.node_coords.get(&b)?.pos(),
+                );
+                if pa.len() > 2 && pb.len() > 2 {
+                    Some(metric::euc_3d(pa, pb))
+                } else {
+                    Some(metric::euc_2d(pa, pb))
+                }
+            })
+            .sum()
+    }
+
+    /// Returns the length of a vehicle route that starts and ends at ```depot```, i.e. the sum of
+    /// the weights ```depot -> route[0] -> ... -> route[last] -> depot```.
+    ///
+    /// The route lists customers only. An empty route has length ```0.```.
+    pub fn route_length(&self, route: &[usize], depot: usize) -> f64 {
+        match (route.first(), route.last()) {
+            (Some(&first), Some(&last)) => {
+                self.weight(depot, first) + self.path_length(route) + self.weight(last, depot)
+            }
+            _ => 0.,
+        }
+    }
+
+    /// Checks whether a set of vehicle routes is a feasible solution for a CVRP.
+    ///
+    /// Every node that is not a depot must appear exactly once across all routes, and the total
+    /// demand of each route must not exceed the capacity. Depots within routes are ignored, so
+    /// routes may be given with or without them. An error describing the first violation is
+    /// returned otherwise.
+    pub fn validate_routes(&self, routes: &[Vec<usize>]) -> Result<(), String> {
+        let customers: HashSet<usize> = self
+            .node_ids()
+            .into_iter()
+            .filter(|id| !self.depots.contains(id))
+            .collect();
+        let mut seen = HashSet::with_capacity(customers.len());
+
+        for (i, route) in routes.iter().enumerate() {
+            let mut load = 0.;
+            for &id in route.iter().filter(|id| !self.depots.contains(id)) {
+                if !customers.contains(&id) {
+                    return Err(format!("route {}: unknown node {}", i, id));
+                }
+                if !seen.insert(id) {
+                    return Err(format!(
+                        "route {}: node {} is visited more than once",
+                        i, id
+                    ));
+                }
+                load += self.demands.get(&id).copied().unwrap_or(0.);
+            }
+
+            if load > self.capacity {
+                return Err(format!(
+                    "route {}: demand {} exceeds capacity {}",
+                    i, load, self.capacity
+                ));
+            }
+        }
+
+        let mut missing: Vec<&usize> = customers.difference(&seen).collect();
+        if !missing.is_empty() {
+            missing.sort_unstable();
+            return Err(format!("node {} is not visited", missing[0]));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the length of each tour stored in the dataset.
+    ///
+    /// See [`Tsp::tour_length`].
+    pub fn tour_lengths(&self) -> Vec<f64> {
+        self.tours.iter().map(|t| self.tour_length(t)).collect()
+    }
+
+    /// Converts a tour into a successor array, where ```succ[i]``` is the node visited after node
+    /// ```i```.
+    ///
+    /// The array is indexed by node ids and has ```dim + 1``` entries, or more if the tour
+    /// contains larger ids. Entries of nodes that are not part of the tour are ```None```.
+    ///
+    /// The entries are optional rather than plain ids because every id can be a valid successor:
+    /// ids start at ```0``` for explicit weights with [`MatrixIndexing::ZeroBased`] and at
+    /// ```1``` otherwise, so no sentinel value can mark the unused entries.
+    pub fn tour_to_successors(&self, tour: &[usize]) -> Vec<Option<usize>> {
+        let len = tour.iter().copied().max().unwrap_or(0).max(self.dim) + 1;
+        let mut succ = vec![None; len];
+        for (a, b) in self.tour_edges(tour) {
+            succ[a] = Some(b);
+        }
+        succ
+    }
+
+    /// Converts a successor array into a tour, starting from the smallest node with a successor.
+    ///
+    /// This is the inverse of [`Tsp::tour_to_successors`] up to rotation of the tour. The tour
+    /// ends when a node is reached for the second time or has no successor.
+    pub fn successors_to_tour(&self, succ: &[Option<usize>]) -> Vec<usize> {
+        let mut node = succ.iter().position(Option::is_some);
+        let mut visited = vec![false; succ.len()];
+        let mut tour = Vec::new();
+        while let Some(id) = node.filter(|&id| id < succ.len() && !visited[id]) {
+            visited[id] = true;
+            tour.push(id);
+            node = succ[id];
+        }
+        tour
+    }
+
+    /// Loads the tours from a file of type [`TspKind::Tour`] and appends them to the tours of this
+    /// dataset.
+    ///
+    /// Each call appends the tours found in the file, so that several tour files can be compared
+    /// against one instance. An error is returned if the file is not a tour file, or if its
+    /// dimension or the length of a tour does not match the dimension of this dataset.
+    pub fn load_tour<P>(&mut self, path: P) -> Result<(), ParseTspError>
+    where
+        P: AsRef<Path>,
+    {
+        let other = TspBuilder::parse_path(path)?;
+
+        if other.kind != TspKind::Tour {
+            return Err(ParseTspError::InvalidInput {
+                key: K_TYPE.to_string(),
+                val: other.kind.tsp_str().to_string(),
+            });
+        }
+
+        if other.dim != 0 && other.dim != self.dim {
+            return Err(ParseTspError::InvalidInput {
+                key: K_DIM.to_string(),
+                val: other.dim.to_string(),
+            });
+        }
+
+        if let Some(tour) = other.tours.iter().find(|t| t.len() != self.dim) {
+            return Err(ParseTspError::InvalidInput {
+                key: K_TOUR_SEC.to_string(),
+                val: format!("tour with {} nodes", tour.len()),
+            });
+        }
+
+        self.tours.extend(other.tours);
+        self.invalidate_cache();
+
+        Ok(())
+    }
+
+    /// Returns all edges of the graph together with their weights.
+    ///
+    /// For a sparse graph given in ```EDGE_DATA_SECTION```, the listed edges are returned. Otherwise
+    /// the graph is complete and an edge is returned for each pair of nodes, in both directions
+    /// for [`TspKind::Atsp`]. Note that the result then has a size of ```O(n²)```.
+    pub fn edge_list(&self) -> Vec<(usize, usize, f64)> {
+        match &self.edge_format {
+            EdgeFormat::EdgeList(v) => v.iter().map(|&(a, b)| (a, b, self.weight(a, b))).collect(),
+            _ => {
+                let ids = self.node_ids();
+                let directed = self.kind == TspKind::Atsp;
+                let mut edges = Vec::with_capacity(ids.len() * ids.len().saturating_sub(1));
+
+                for (i, &a) in ids.iter().enumerate() {
+                    for &b in ids.iter().skip(i + 1) {
+                        edges.push((a, b, self.weight(a, b)));
+                        if directed {
+                            edges.push((b, a, self.weight(b, a)));
+                        }
+                    }
+                }
+
+                edges
+            }
+        }
+    }
+
+    /// Returns the minimum, maximum, mean and (population) standard deviation of the weights
+    /// between all pairs of distinct nodes.
+    ///
+    /// Pairs are counted in both directions for [`TspKind::Atsp`]. For explicit instances, the
+    /// weights are read from the matrix. Note that the computation takes ```O(n²)``` time. If the
+    /// dataset has less than two nodes, all values are ```0.```.
+    pub fn edge_weight_stats(&self) -> (f64, f64, f64, f64) {
+        let ids = self.node_ids();
+        let directed = self.kind == TspKind::Atsp;
+
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        let (mut cnt, mut mean, mut m2) = (0_usize, 0_f64, 0_f64);
+        let mut add = |w: f64| {
+            min = min.min(w);
+            max = max.max(w);
+            // Welford's online algorithm.
+            cnt += 1;
+            let delta = w - mean;
+            mean += delta / cnt as f64;
+            m2 += delta * (w - mean);
+        };
+
+        for (i, &a) in ids.iter().enumerate() {
+            for &b in ids.iter().skip(i + 1) {
+                add(self.weight(a, b));
+                if directed {
+                    add(self.weight(b, a));
+                }
+            }
+        }
+
+        if cnt == 0 {
+            (0., 0., 0., 0.)
+        } else {
+            (min, max, mean, (m2 / cnt as f64).sqrt())
+        }
+    }
+
+    /// Checks whether the dataset uses a Euclidean metric.
+    ///
+    /// See [`WeightKind::is_euclidean`].
+    pub fn is_euclidean(&self) -> bool {
+        self.weight_kind.is_euclidean()
+    }
+
+    /// Checks whether any edge weight is negative.
+    ///
+    /// Only explicit edge weights can be negative, so this returns ```false``` without scanning
+    /// for weights computed from node coordinates. Methods such as [`Tsp::one_tree_bound`] assume
+    /// non-negative weights.
+    pub fn has_negative_weights(&self) -> bool {
+        self.weight_kind == WeightKind::Explicit
+            && self.edge_weights.iter().flatten().any(|w| *w < 0.)
+    }
+
+    /// Returns the weight of a minimum 1-tree, which is a lower bound for the length of an optimal
+    /// tour.
+    ///
+    /// The 1-tree consists of a minimum spanning tree on all nodes except ```special```, plus the
+    /// two cheapest edges connecting ```special``` to the other nodes. Weights are treated as
+    /// symmetric. The computation takes ```O(n²)``` time.
+    pub fn one_tree_bound(&self, special: usize) -> f64 {
+        let ids: Vec<usize> = self
+            .node_ids()
+            .into_iter()
+            .filter(|&id| id != special)
+            .collect();
+
+        let mut ws: Vec<f64> = ids.iter().map(|&id| self.weight(special, id)).collect();
+        ws.sort_by(|a, b| a.total_cmp(b));
+
+        self.mst_weight_of(&ids) + ws.iter().take(2).sum::<f64>()
+    }
+
+    /// Returns the weight of a minimum spanning tree on the given nodes, computed with Prim's
+    /// algorithm.
+    fn mst_weight_of(&self, ids: &[usize]) -> f64 {
+        let n = ids.len();
+        let mut in_tree = vec![false; n];
+        let mut dist = vec![f64::INFINITY; n];
+        let mut total = 0.;
+
+        if n > 0 {
+            dist[0] = 0.;
+        }
+
+        for _ in 0..n {
+            let i = (0..n)
+                .filter(|&i| !in_tree[i])
+                .min_by(|&i, &j| dist[i].total_cmp(&dist[j]))
+                .unwrap();
+            in_tree[i] = true;
+            total += dist[i];
+
+            for j in 0..n {
+                if !in_tree[j] {
+                    dist[j] = dist[j].min(self.weight(ids[i], ids[j]));
+                }
+            }
+        }
+
+        total
+    }
+
+    /// Returns the minimum and maximum weight between all pairs of distinct nodes.
+    ///
+    /// The bounds are computed with [`Tsp::edge_weight_stats`] on the first call and cached
+    /// afterwards. The cache is reset by the mutating methods of [`Tsp`], such as
+    /// [`Tsp::map_coords`], and by the mutable accessors, such as [`Tsp::node_coords_mut`].
+    pub fn weight_bounds(&self) -> (f64, f64) {
+        *self.weight_bounds.get_or_init(|| {
+            let (min, max, _, _) = self.edge_weight_stats();
+            (min, max)
+        })
+    }
+
+    /// Returns the edge weight between two nodes, scaled to ```[0, 1]``` by the bounds of
+    /// [`Tsp::weight_bounds`].
+    ///
+    /// If all weights are equal, the function returns ```0.``` to avoid a division by zero.
+    pub fn normalized_weight(&self, a: usize, b: usize) -> f64 {
+        let (min, max) = self.weight_bounds();
+        if max > min {
+            (self.weight(a, b) - min) / (max - min)
+        } else {
+            0.
+        }
+    }
+
+    /// Returns the ids of the nodes on the convex hull of the node coordinates, in
+    /// counter-clockwise order.
+    ///
+    /// The hull is computed with the monotone chain algorithm and starts at the node with the
+    /// smallest coordinates. Nodes lying on an edge of the hull are not included. Returns an empty
+    /// vector if the dataset has no two-dimensional node coordinates.
+    pub fn convex_hull(&self) -> Vec<usize> {
+        if self.coord_kind != CoordKind::Coord2d
+            || self.node_coords.values().any(|pt| pt.pos.len() < 2)
+        {
+            return Vec::new();
+        }
+
+        let mut pts: Vec<(usize, f64, f64)> = self
+            .node_coords
+            .values()
+            .map(|pt| (pt.id, pt.pos[0], pt.pos[1]))
+            .collect();
+        pts.sort_by(|a, b| {
+            a.1.total_cmp(&b.1)
+                .then(a.2.total_cmp(&b.2))
+                .then(a.0.cmp(&b.0))
+        });
+        pts.dedup_by(|a, b| a.1 == b.1 && a.2 == b.2);
+
+        if pts.len() < 3 {
+            return pts.iter().map(|p| p.0).collect();
+        }
+
+        // Cross product of the vectors oa and ob.
+        let cross = |o: &(usize, f64, f64), a: &(usize, f64, f64), b: &(usize, f64, f64)| {
+            (a.1 - o.1) * (b.2 - o.2) - (a.2 - o.2) * (b.1 - o.1)
+        };
+
+        let mut hull: Vec<(usize, f64, f64)> = Vec::with_capacity(pts.len() + 1);
+
+        // Lower chain.
+        for p in pts.iter() {
+            while hull.len() >= 2 && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], p) <= 0. {
+                hull.pop();
+            }
+            hull.push(*p);
+        }
+
+        // Upper chain.
+        let lower_len = hull.len() + 1;
+        for p in pts.iter().rev().skip(1) {
+            while hull.len() >= lower_len
+                && cross(&hull[hull.len() - 2], &hull[hull.len() - 1], p) <= 0.
+            {
+                hull.pop();
+            }
+            hull.push(*p);
+        }
+
+        // The last point is the same as the first one.
+        hull.pop();
+
+        hull.iter().map(|p| p.0).collect()
+    }
+
+    /// Returns the number of pairs of non-adjacent edges of a closed tour that cross each other
+    /// in the plane of the node coordinates.
+    ///
+    /// Only proper crossings are counted, i.e. edges that merely touch or overlap collinearly are
+    /// not. The computation takes ```O(n²)``` time. Returns ```None``` if the dataset has no
+    /// two-dimensional node coordinates or a node of the tour has no coordinates.
+    pub fn tour_crossings(&self, tour: &[usize]) -> Option<usize> {
+        if self.coord_kind != CoordKind::Coord2d {
+            return None;
+        }
+
+        let segs = self
+            .tour_edges(tour)
+            .map(|(a, b)| {
+                let (pa, pb) = (self.node_coords.get(&a)?, self.node_coords.get(&b)?);
+                Some(((pa.pos[0], pa.pos[1]), (pb.pos[0], pb.pos[1])))
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        // Orientation of the point r relative to the line through p and q.
+        let orient = |p: (f64, f64), q: (f64, f64), r: (f64, f64)| {
+            let v = (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0);
+            if v > 0. {
+                1
+            } else if v < 0. {
+                -1
+            } else {
+                0
+            }
+        };
+
+        let n = segs.len();
+        let mut cnt = 0;
+        for i in 0..n {
+            // Skip the adjacent edges i + 1 and, for the first edge, the last one.
+            for j in (i + 2)..n {
+                if i == 0 && j == n - 1 {
+                    continue;
+                }
+
+                let ((p1, p2), (q1, q2)) = (segs[i], segs[j]);
+                let (o1, o2) = (orient(p1, p2, q1), orient(p1, p2, q2));
+                let (o3, o4) = (orient(q1, q2, p1), orient(q1, q2, p2));
+                if o1 * o2 < 0 && o3 * o4 < 0 {
+                    cnt += 1;
+                }
+            }
+        }
+
+        Some(cnt)
+    }
+
+    /// Returns the number of edges of the graph.
+    ///
+    /// For a sparse graph given in ```EDGE_DATA_SECTION```, this is the number of listed edges.
+    /// Otherwise the graph is complete and the number of all node pairs is returned, counted in
+    /// both directions for [`TspKind::Atsp`]. See also [`Tsp::edge_list`].
+    pub fn edge_count(&self) -> usize {
+        match &self.edge_format {
+            EdgeFormat::EdgeList(v) => v.len(),
+            _ => {
+                let n = self.dim * self.dim.saturating_sub(1);
+                if self.kind == TspKind::Atsp {
+                    n
+                } else {
+                    n / 2
+                }
+            }
+        }
+    }
+
+    /// Returns the density of the graph, i.e. the ratio of the number of edges to the number of
+    /// all node pairs ```dim * (dim - 1) / 2```.
+    ///
+    /// The density of a complete graph is always ```1.```. For a sparse graph with less than two
+    /// nodes, ```0.``` is returned.
+    pub fn density(&self) -> f64 {
+        match &self.edge_format {
+            EdgeFormat::EdgeList(v) => {
+                let pairs = self.dim * self.dim.saturating_sub(1) / 2;
+                if pairs == 0 {
+                    0.
+                } else {
+                    v.len() as f64 / pairs as f64
+                }
+            }
+            _ => 1.,
+        }
+    }
+
+    /// Applies a transformation to the coordinates of every node.
+    ///
+    /// The closure receives the current coordinates of a node and returns its new coordinates,
+    /// e.g. to project geographical coordinates onto a plane. All weights computed afterwards are
+    /// based on the new coordinates, so any previously computed distances are invalidated.
+    ///
+    /// The closure may change the number of coordinates. If all nodes end up with two or three
+    /// coordinates, [`Tsp::coord_kind`] is updated accordingly. Note that the weight type is not
+    /// changed.
+    pub fn map_coords<F>(&mut self, f: F)
+    where
+        F: Fn(&[f64]) -> Vec<f64>,
+    {
+        for pt in self.node_coords.values_mut() {
+            pt.pos = f(&pt.pos);
+        }
+
+        let mut lens = self.node_coords.values().map(|pt| pt.pos.len());
+        if let Some(len) = lens.next() {
+            if lens.all(|l| l == len) {
+                match len {
+                    2 => self.coord_kind = CoordKind::Coord2d,
+                    3 => self.coord_kind = CoordKind::Coord3d,
+                    _ => {}
+                }
+            }
+        }
+
+        self.invalidate_cache();
+    }
+
+    /// Returns a copy of the dataset whose edge weights are stored explicitly in the given format.
+    ///
+    /// All weights are computed with [`Tsp::weight`] and the copy has the weight type
+    /// [`WeightKind::Explicit`]. Node coordinates are kept, e.g. for display purposes. The node
+    /// with the ```i```-th smallest id maps to row ```i``` of the matrix. Unless the dataset
+    /// already has explicit weights, the copy uses [`MatrixIndexing::OneBased`], so that it
+    /// accepts the same ids as the original if these are ```1..=dim```. Its TSPLIB output must
+    /// therefore be read with the same indexing, see [`MatrixIndexing`].
+    ///
+    /// Triangular formats store a single weight for each pair of nodes and should only be used
+    /// for symmetric datasets. An error is returned for [`WeightFormat::Function`] and
+    /// [`WeightFormat::Undefined`].
+    pub fn to_explicit(&self, format: WeightFormat) -> Result<Tsp, ParseTspError> {
+        if let WeightFormat::Function | WeightFormat::Undefined = format {
+            return Err(ParseTspError::InvalidInput {
+                key: String::from(K_WEIGHT_FORMAT),
+                val: String::from(format.tsp_str()),
+            });
+        }
+
+        let ids = self.node_ids();
+        let mut tsp = self.clone();
+        tsp.weight_format = format;
+        tsp.edge_weights = format
+            .row_lens(ids.len())
+            .into_iter()
+            .map(|len| vec![0.; len])
+            .collect();
+        for (i, &a) in ids.iter().enumerate() {
+            for (j, &b) in ids.iter().enumerate() {
+                if let Some((r, c)) = tsp.matrix_cell(i, j) {
+                    tsp.edge_weights[r][c] = self.weight(a, b);
+                }
+            }
+        }
+
+        if self.weight_kind != WeightKind::Explicit {
+            tsp.weight_kind = WeightKind::Explicit;
+            tsp.matrix_indexing = MatrixIndexing::OneBased;
+        }
+        tsp.present_sections.edge_weights = true;
+        tsp.invalidate_cache();
+
+        Ok(tsp)
+    }
+
+    /// Relabels the nodes of the dataset with a permutation of their ids.
+    ///
+    /// The node with the ```i```-th smallest id receives the id ```perm[i]```. All sections are
+    /// updated accordingly, so that the geometry and all weights are preserved, i.e.
+    /// ```weight(a, b)``` before equals ```weight(perm[a'], perm[b'])``` afterwards, where
+    /// ```a'``` and ```b'``` are the positions of ```a``` and ```b``` among the sorted ids.
+    ///
+    /// The nodes of sparse graphs without coordinates are numbered ```1..=dim``` as in TSPLIB.
+    /// An error is returned if ```perm``` is not a permutation of the node ids or if an edge
+    /// refers to a node outside of them, in which case the dataset is left unchanged.
+    pub fn relabel(&mut self, perm: &[usize]) -> Result<(), ParseTspError> {
+        let ids = match &self.edge_format {
+            EdgeFormat::EdgeList(_) if self.node_coords.is_empty() => (1..=self.dim).collect(),
+            _ => self.node_ids(),
+        };
+        let mut sorted = perm.to_vec();
+        sorted.sort_unstable();
+        if sorted != ids {
+            return Err(ParseTspError::Other("invalid permutation of node ids"));
+        }
+        let edges = match &self.edge_format {
+            EdgeFormat::EdgeList(edges) => edges.as_slice(),
+            _ => &[],
+        };
+        if self
+            .fixed_edges
+            .iter()
+            .chain(edges)
+            .any(|(a, b)| ids.binary_search(a).is_err() || ids.binary_search(b).is_err())
+        {
+            return Err(ParseTspError::Other("edge refers to an unknown node id"));
+        }
+
+        let map: HashMap<usize, usize> = ids.iter().copied().zip(perm.iter().copied()).collect();
+        let new_id = |id: usize| map.get(&id).copied().unwrap_or(id);
+
+        if self.weight_kind == WeightKind::Explicit && !self.edge_weights.is_empty() {
+            let mut weights = self.edge_weights.clone();
+            for &a in &ids {
+                for &b in &ids {
+                    let (na, nb) = (new_id(a), new_id(b));
+                    if let (Some(ia), Some(ib)) = (self.matrix_index(na), self.matrix_index(nb)) {
+                        if let Some((r, c)) = self.matrix_cell(ia, ib) {
+                            weights[r][c] = self.weight(a, b);
+                        }
+                    }
+                }
+            }
+            self.edge_weights = weights;
+        }
+
+        self.node_coords = std::mem::take(&mut self.node_coords)
+            .into_values()
+            .map(|mut pt| {
+                pt.id = new_id(pt.id);
+                (pt.id, pt)
+            })
+            .collect();
+        self.disp_coords
+            .iter_mut()
+            .for_each(|pt| pt.id = new_id(pt.id));
+        self.demands = std::mem::take(&mut self.demands)
+            .into_iter()
+            .map(|(id, d)| (new_id(id), d))
+            .collect();
+        self.node_labels = std::mem::take(&mut self.node_labels)
+            .into_iter()
+            .map(|(id, label)| (new_id(id), label))
+            .collect();
+        self.pickup_delivery = std::mem::take(&mut self.pickup_delivery)
+            .into_iter()
+            .map(|(id, pd)| (new_id(id), pd))
+            .collect();
+        self.time_windows = std::mem::take(&mut self.time_windows)
+            .into_iter()
+            .map(|(id, tw)| (new_id(id), tw))
+            .collect();
+        self.depots = std::mem::take(&mut self.depots)
+            .into_iter()
+            .map(new_id)
+            .collect();
+        self.depot_order.iter_mut().for_each(|id| *id = new_id(*id));
+        let edges = match &mut self.edge_format {
+            EdgeFormat::EdgeList(edges) => Some(edges),
+            _ => None,
+        };
+        for (a, b) in self
+            .fixed_edges
+            .iter_mut()
+            .chain(edges.into_iter().flatten())
+        {
+            *a = new_id(*a);
+            *b = new_id(*b);
+        }
+        self.tours
+            .iter_mut()
+            .chain(self.clusters.iter_mut())
+            .flatten()
+            .for_each(|id| *id = new_id(*id));
+
+        self.invalidate_cache();
+
+        Ok(())
+    }
+
+    /// Returns the sub-instance of all nodes whose coordinates lie within a box.
+    ///
+    /// A node is kept if ```min[k] <= pos[k] <= max[k]``` holds for each coordinate ```k```. The
+    /// kept nodes are renumbered to ```1..=n``` in the order of their original ids, and all
+    /// sections are restricted to them: tours and clusters keep the order of the remaining nodes,
+    /// while edges, fixed edges and depots outside the box are dropped.
+    ///
+    /// An error is returned if the dataset has no node coordinates or if the dimension of the
+    /// box does not match [`Tsp::coord_dimension`].
+    pub fn nodes_in_box(&self, min: &[f64], max: &[f64]) -> Result<Tsp, ParseTspError> {
+        if self.node_coords.is_empty() {
+            return Err(ParseTspError::MissingEntry(String::from(K_NODE_COORD_SEC)));
+        }
+        if Some(min.len()) != self.coord_dimension() || Some(max.len()) != self.coord_dimension() {
+            return Err(ParseTspError::Other(
+                "dimension of box does not match coordinates",
+            ));
+        }
+
+        let kept: Vec<usize> = self
+            .node_ids()
+            .into_iter()
+            .filter(|id| {
+                let pos = self.node_coords[id].pos();
+                (0..pos.len()).all(|k| min[k] <= pos[k] && pos[k] <= max[k])
+            })
+            .collect();
+        let map: HashMap<usize, usize> = kept
+            .iter()
+            .enumerate()
+            .map(|(idx, &id)| (id, idx + 1))
+            .collect();
+        let new_id = |id: &usize| map.get(id).copied();
+
+        let mut tsp = self.clone();
+        tsp.dim = kept.len();
+
+        if self.weight_kind == WeightKind::Explicit && !self.edge_weights.is_empty() {
+            tsp.matrix_indexing = MatrixIndexing::OneBased;
+            tsp.edge_weights = self
+                .weight_format
+                .row_lens(kept.len())
+                .into_iter()
+                .map(|len| vec![0.; len])
+                .collect();
+            for (i, &a) in kept.iter().enumerate() {
+                for (j, &b) in kept.iter().enumerate() {
+                    if let Some((r, c)) = tsp.matrix_cell(i, j) {
+                        tsp.edge_weights[r][c] = self.weight(a, b);
+                    }
+                }
+            }
+        }
+
+        tsp.node_coords = self
+            .node_coords
+            .values()
+            .filter_map(|pt| {
+                new_id(&pt.id).map(|id| {
+                    let mut pt = pt.clone();
+                    pt.id = id;
+                    (id, pt)
+                })
+            })
+            .collect();
+        tsp.disp_coords = self
+            .disp_coords
+            .iter()
+            .filter_map(|pt| {
+                new_id(&pt.id).map(|id| {
+                    let mut pt = pt.clone();
+                    pt.id = id;
+                    pt
+                })
+            })
+            .collect();
+        tsp.disp_coords.sort_by_key(|pt| pt.id);
+        tsp.demands = self
+            .demands
+            .iter()
+            .filter_map(|(id, &d)| new_id(id).map(|id| (id, d)))
+            .collect();
+        tsp.node_labels = self
+            .node_labels
+            .iter()
+            .filter_map(|(id, label)| new_id(id).map(|id| (id, label.clone())))
+            .collect();
+        tsp.pickup_delivery = self
+            .pickup_delivery
+            .iter()
+            .filter_map(|(id, &pd)| new_id(id).map(|id| (id, pd)))
+            .collect();
+        tsp.time_windows = self
+            .time_windows
+            .iter()
+            .filter_map(|(id, &tw)| new_id(id).map(|id| (id, tw)))
+            .collect();
+        tsp.depots = self.depots.iter().filter_map(new_id).collect();
+        tsp.depot_order = self.depot_order.iter().filter_map(new_id).collect();
+
+        let restrict_edges = |edges: &[(usize, usize)]| -> Vec<(usize, usize)> {
+            edges
+                .iter()
+                .filter_map(|(a, b)| Some((new_id(a)?, new_id(b)?)))
+                .collect()
+        };
+        tsp.fixed_edges = restrict_edges(&self.fixed_edges);
+        if let EdgeFormat::EdgeList(edges) = &self.edge_format {
+            tsp.edge_format = EdgeFormat::EdgeList(restrict_edges(edges));
+        }
+        tsp.tours = self
+            .tours
+            .iter()
+            .map(|tour| tour.iter().filter_map(new_id).collect())
+            .collect();
+        tsp.clusters = self
+            .clusters
+            .iter()
+            .map(|set| set.iter().filter_map(new_id).collect())
+            .collect();
+
+        tsp.invalidate_cache();
+
+        Ok(tsp)
+    }
+
+    /// Checks the internal invariants of the dataset and returns a description of each violation.
+    ///
+    /// Unlike the validation performed during parsing, this function also inspects the data part
+    /// in depth, which makes it useful for instances that were modified after parsing. The
+    /// following properties are checked:
+    /// - the number of node coordinates and display coordinates matches the dimension.
+    /// - each tour is a permutation of the node ids.
+    /// - for [`TspKind::Cvrp`], every node has a demand and every depot is a known node.
+    /// - each fixed edge connects known nodes.
+    /// - for [`WeightKind::Explicit`], the shape of the weight matrix matches the weight format.
+    ///
+    /// An empty vector means that no inconsistency was found.
+    pub fn check_consistency(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let is_node = |id: &usize| (1..=self.dim).contains(id);
+
+        if !self.node_coords.is_empty() {
+            if self.node_coords.len() != self.dim {
+                problems.push(format!(
+                    "{} node coordinates given for dimension {}",
+                    self.node_coords.len(),
+                    self.dim
+                ));
+            }
+
+            for (id, pt) in &self.node_coords {
+                if *id != pt.id() {
+                    problems.push(format!(
+                        "node coordinate {} is stored under id {}",
+                        pt.id(),
+                        id
+                    ));
+                }
+                if !is_node(id) {
+                    problems.push(format!("node coordinate has invalid id {}", id));
+                }
+            }
+        }
+
+        if !self.disp_coords.is_empty() && self.disp_coords.len() != self.dim {
+            problems.push(format!(
+                "{} display coordinates given for dimension {}",
+                self.disp_coords.len(),
+                self.dim
+            ));
+        }
+
+        for (idx, tour) in self.tours.iter().enumerate() {
+            let mut seen = HashSet::with_capacity(tour.len());
+            for id in tour {
+                if !is_node(id) {
+                    problems.push(format!("tour {} contains invalid node id {}", idx, id));
+                } else if !seen.insert(*id) {
+                    problems.push(format!("tour {} visits node {} more than once", idx, id));
+                }
+            }
+
+            if tour.len() != self.dim {
+                problems.push(format!(
+                    "tour {} has {} nodes for dimension {}",
+                    idx,
+                    tour.len(),
+                    self.dim
+                ));
+            }
+        }
+
+        if self.kind == TspKind::Cvrp {
+            for id in 1..=self.dim {
+                if !self.demands.contains_key(&id) {
+                    problems.push(format!("node {} has no demand", id));
                 }
-                WeightFormat::LowerRow | WeightFormat::UpperCol => match a.cmp(&b) {
-                    std::cmp::Ordering::Less => self.edge_weights[b - 1][a],
-                    std::cmp::Ordering::Equal => 0.,
-                    std::cmp::Ordering::Greater => self.edge_weights[a - 1][b],
-                },
-                WeightFormat::LowerDiagRow | WeightFormat::UpperDiagCol => {
-                    if a < b {
-                        self.edge_weights[b][a]
-                    } else {
-                        self.edge_weights[a][b]
-                    }
+            }
+
+            for id in &self.depots {
+                if !is_node(id) {
+                    problems.push(format!("depot has invalid node id {}", id));
                 }
-                WeightFormat::Undefined => 0.,
-            },
-            _ => {
-                if let (Some(na), Some(nb)) = (self.node_coords.get(&a), self.node_coords.get(&b)) {
-                    self.weight_kind.cost(na.pos(), nb.pos())
-                } else {
-                    0.
+            }
+        }
+
+        for (f, l) in &self.fixed_edges {
+            if !is_node(f) || !is_node(l) {
+                problems.push(format!("fixed edge ({}, {}) has an invalid node id", f, l));
+            }
+        }
+
+        if self.weight_kind == WeightKind::Explicit {
+            let row_lens = self.weight_format.row_lens(self.dim);
+            if row_lens.len() != self.edge_weights.len() {
+                problems.push(format!(
+                    "weight matrix has {} rows, expected {} for {}",
+                    self.edge_weights.len(),
+                    row_lens.len(),
+                    self.weight_format
+                ));
+            }
+
+            for (idx, (row, len)) in self.edge_weights.iter().zip(row_lens).enumerate() {
+                if row.len() != len {
+                    problems.push(format!(
+                        "row {} of weight matrix has {} entries, expected {}",
+                        idx,
+                        row.len(),
+                        len
+                    ));
                 }
             }
         }
+
+        problems
     }
 }
 
@@ -273,6 +1909,9 @@ pub struct TspBuilder {
     comment: Option<String>,
     dim: Option<usize>,
     capacity: Option<f64>,
+    scale: Option<f64>,
+    gtsp_sets: Option<usize>,
+    vehicles: Option<usize>,
     weight_kind: Option<WeightKind>,
     weight_format: Option<WeightFormat>,
     edge_format: Option<EdgeFormat>,
@@ -280,12 +1919,31 @@ pub struct TspBuilder {
     disp_kind: Option<DisplayKind>,
     // Data
     coords: Option<HashMap<usize, Point>>,
+    labels: Option<HashMap<usize, String>>,
     depots: Option<HashSet<usize>>,
+    depot_order: Vec<usize>,
     demands: Option<HashMap<usize, f64>>,
+    pickup_delivery: Option<HashMap<usize, (f64, f64)>>,
     edge_weights: Option<Vec<Vec<f64>>>,
     disp_coords: Option<Vec<Point>>,
     fixed_edges: Option<Vec<(usize, usize)>>,
     tours: Option<Vec<Vec<usize>>>,
+    time_windows: Option<HashMap<usize, (f64, f64)>>,
+    clusters: Option<Vec<Vec<usize>>>,
+    raw_lines: Option<Vec<String>>,
+    trailer: String,
+    spec_keys: Vec<String>,
+    warnings: Vec<String>,
+    // Options
+    implicit_ids: bool,
+    preserve_raw: bool,
+    capture_trailer: bool,
+    decimal_comma: bool,
+    geo_lon_lat: bool,
+    apply_scale: bool,
+    matrix_indexing: MatrixIndexing,
+    rounding_policy: RoundingPolicy,
+    default_weight_kind: Option<WeightKind>,
 }
 
 impl TspBuilder {
@@ -295,18 +1953,285 @@ impl TspBuilder {
         }
     }
 
+    /// Sets whether node ids are omitted in ```NODE_COORD_SECTION```.
+    ///
+    /// If set, each row of the section only contains the coordinates of a node, and ids are
+    /// assigned as ```1..=dim``` in the order of rows. Disabled by default.
+    pub fn implicit_ids(mut self, implicit_ids: bool) -> Self {
+        self.implicit_ids = implicit_ids;
+        self
+    }
+
+    /// Sets whether the original lines of the input are kept.
+    ///
+    /// If set, [`Tsp::write_tsplib`] reproduces the input verbatim, including formatting, the
+    /// order of entries and all lines after ```EOF```. Line breaks are always written as ```\n```
+    /// and a final line break is added if missing. The lines are discarded as soon as the dataset
+    /// is changed, including through a mutable accessor such as [`Tsp::node_coords_mut`], after
+    /// which the data is written as usual. This trades memory for fidelity. Disabled by default.
+    pub fn preserve_raw(mut self, preserve_raw: bool) -> Self {
+        self.preserve_raw = preserve_raw;
+        self
+    }
+
+    /// Sets whether the lines after ```EOF``` are kept.
+    ///
+    /// Some files append metadata after ```EOF```, e.g. the length of the best known tour. If set,
+    /// these lines are available through [`Tsp::trailer`]. Disabled by default.
+    pub fn capture_trailer(mut self, capture_trailer: bool) -> Self {
+        self.capture_trailer = capture_trailer;
+        self
+    }
+
+    /// Sets whether numbers use a comma as decimal separator, e.g. ```38,24```.
+    ///
+    /// If set, commas are replaced with points in ```NODE_COORD_SECTION```, ```DEMAND_SECTION```
+    /// and ```EDGE_WEIGHT_SECTION``` before parsing, including commas in node labels. Disabled by
+    /// default.
+    pub fn decimal_comma(mut self, decimal_comma: bool) -> Self {
+        self.decimal_comma = decimal_comma;
+        self
+    }
+
+    /// Sets whether geographical coordinates are given in the order ```longitude latitude```.
+    ///
+    /// TSPLIB stores coordinates for [`WeightKind::Geo`] as ```latitude longitude```, which is the
+    /// default. Some converted datasets swap the order, which this option accounts for when
+    /// computing distances. The stored coordinates are not changed.
+    pub fn geo_lon_lat_order(mut self, lon_lat: bool) -> Self {
+        self.geo_lon_lat = lon_lat;
+        self
+    }
+
+    /// Sets whether node coordinates are multiplied by the factor given in ```SCALE```.
+    ///
+    /// By default, coordinates are kept as given in the input and the factor is only available
+    /// through [`Tsp::scale_factor`].
+    pub fn apply_scale(mut self, apply_scale: bool) -> Self {
+        self.apply_scale = apply_scale;
+        self
+    }
+
+    /// Sets how node ids map to rows and columns of an explicit weight matrix.
+    ///
+    /// Defaults to [`MatrixIndexing::ZeroBased`]. Use [`MatrixIndexing::OneBased`], the
+    /// convention of TSPLIB, so that the ids of tours and fixed edges in the same file refer to
+    /// the matrix rows. Ids without a row of the matrix have a weight of ```0```.
+    pub fn matrix_indexing(mut self, indexing: MatrixIndexing) -> Self {
+        self.matrix_indexing = indexing;
+        self
+    }
+
+    /// Sets how distances of [`WeightKind::Geo`] are rounded to integers.
+    ///
+    /// Defaults to [`RoundingPolicy::Truncate`] as in TSPLIB.
+    pub fn rounding_policy(mut self, policy: RoundingPolicy) -> Self {
+        self.rounding_policy = policy;
+        self
+    }
+
+    /// Sets the edge weight type of datasets with node coordinates that omit
+    /// ```EDGE_WEIGHT_TYPE```.
+    ///
+    /// By default, such datasets are rejected with [`ParseTspError::MissingEntry`]. If set, the
+    /// given type is used instead and a warning is reported by
+    /// [`TspBuilder::build_with_warnings`].
+    pub fn default_weight_kind(mut self, kind: WeightKind) -> Self {
+        self.default_weight_kind = Some(kind);
+        self
+    }
+
+    /// Parses an input string with the options set in this builder.
+    ///
+    /// See [`TspBuilder::parse_str`].
+    pub fn read_str<S>(self, s: S) -> Result<Tsp, ParseTspError>
+    where
+        S: AsRef<str>,
+    {
+        self.load_str(s)?.build()
+    }
+
+    /// Parses the content of a file given from a path with the options set in this builder.
+    ///
+    /// See [`TspBuilder::parse_path`].
+    pub fn read_path<P>(self, path: P) -> Result<Tsp, ParseTspError>
+    where
+        P: AsRef<Path>,
+    {
+        self.load_path(path)?.build()
+    }
+
+    /// Parses the content of a reader with the options set in this builder.
+    ///
+    /// See [`TspBuilder::parse_reader`].
+    pub fn read_reader<R>(self, reader: R) -> Result<Tsp, ParseTspError>
+    where
+        R: BufRead,
+    {
+        self.load_reader(reader)?.build()
+    }
+
+    /// Parses a dataset split into two files with the options set in this builder.
+    ///
+    /// See [`TspBuilder::parse_path_with_data`].
+    pub fn read_path_with_data<P>(self, spec: P, data: P) -> Result<Tsp, ParseTspError>
+    where
+        P: AsRef<Path>,
+    {
+        self.load_path(spec)?.load_path(data)?.build()
+    }
+
+    /// Parses an input string that may lack the specification part with the options set in this
+    /// builder.
+    ///
+    /// See [`TspBuilder::parse_str_headerless`].
+    pub fn read_str_headerless<S>(mut self, s: S) -> Result<Tsp, ParseTspError>
+    where
+        S: AsRef<str>,
+    {
+        let lines: Vec<&str> = s.as_ref().lines().map(|l| l.trim()).collect();
+        let start = match lines.iter().position(|l| l.starts_with(K_NODE_COORD_SEC)) {
+            Some(idx) => idx + 1,
+            None => return Err(ParseTspError::MissingEntry(String::from(K_NODE_COORD_SEC))),
+        };
+
+        let rows: Vec<&str> = lines[start..]
+            .iter()
+            .filter(|l| !l.is_empty())
+            .take_while(|l| l.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c)))
+            .copied()
+            .collect();
+        let n_tokens = rows
+            .first()
+            .map(|r| r.split_whitespace().count())
+            .unwrap_or(0)
+            .saturating_sub(usize::from(!self.implicit_ids));
+
+        let weight_kind = if n_tokens == 3 {
+            WeightKind::Euc3d
+        } else {
+            WeightKind::Euc2d
+        };
+
+        self.name = Some(String::new());
+        self.kind = Some(TspKind::Tsp);
+        self.dim = Some(rows.len());
+        self.weight_kind = Some(weight_kind);
+        self.coord_kind = Some(CoordKind::from(weight_kind));
+
+        self.load_it(&mut lines.into_iter())?.build()
+    }
+
+    /// Parses an input string into this builder without constructing a [`Tsp`] object.
+    ///
+    /// This allows to finish the construction with either [`TspBuilder::build`] or
+    /// [`TspBuilder::build_with_warnings`].
+    pub fn load_str<S>(self, s: S) -> Result<Self, ParseTspError>
+    where
+        S: AsRef<str>,
+    {
+        let mut itr = s.as_ref().lines();
+        self.load_it(&mut itr)
+    }
+
+    /// Parses the content of a reader into this builder without constructing a [`Tsp`] object.
+    ///
+    /// Lines are read and parsed one at a time, so that apart from the parsed data only the
+    /// current line is held in memory. This does not apply if the input is kept with
+    /// [`TspBuilder::preserve_raw`]. An error is returned if the reader fails or yields invalid
+    /// UTF-8. See [`TspBuilder::load_str`].
+    pub fn load_reader<R>(self, reader: R) -> Result<Self, ParseTspError>
+    where
+        R: BufRead,
+    {
+        // Reading stops at the first error, which takes precedence over any parsing error caused
+        // by the truncated input.
+        let mut error = None;
+        let result = {
+            let mut lines_it = reader
+                .lines()
+                .map_while(|l| l.map_err(|e| error = Some(e)).ok());
+            self.load_it(&mut lines_it)
+        };
+        match error {
+            Some(e) => Err(e.into()),
+            None => result,
+        }
+    }
+
+    /// Parses the content of a file given from a path into this builder without constructing a
+    /// [`Tsp`] object.
+    ///
+    /// See [`TspBuilder::load_str`].
+    pub fn load_path<P>(self, path: P) -> Result<Self, ParseTspError>
+    where
+        P: AsRef<Path>,
+    {
+        if path.as_ref().is_dir() {
+            return Err(ParseTspError::Other("Path is a directory"));
+        }
+
+        let stem = path
+            .as_ref()
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned());
+        let file = File::open(path)?;
+        let mut builder = self.load_reader(BufReader::new(file))?;
+
+        if let (Some(name), Some(stem)) = (builder.name.as_mut(), stem) {
+            if name.is_empty() {
+                builder
+                    .warnings
+                    .push(format!("{}: empty, using file name {}", K_NAME, stem));
+                *name = stem;
+            }
+        }
+
+        Ok(builder)
+    }
+
     /// Parses an input string.
     ///
     /// If all entries in the input string are valid, a [`Tsp`] object will be returned. Otherwise,
-    /// an error [`ParseTspError`] is returned, containing hints why the parsing fails.
+    /// an error [`ParseTspError`] is returned, containing hints why the parsing fails. For large
+    /// inputs, prefer [`TspBuilder::parse_reader`], which does not require the whole input in
+    /// memory.
     // Should be in TryFrom once issue 50133 is fixed.
     // See: https://github.com/rust-lang/rust/issues/50133.
     pub fn parse_str<S>(s: S) -> Result<Tsp, ParseTspError>
     where
         S: AsRef<str>,
     {
-        let mut itr = s.as_ref().lines();
-        Self::parse_it(&mut itr)
+        Self::new().read_str(s)
+    }
+
+    /// Parses an input string that may consist of a bare ```NODE_COORD_SECTION``` without any
+    /// specification part.
+    ///
+    /// The dimension is inferred from the number of coordinate rows. Unless given in the input,
+    /// the type defaults to [`TspKind::Tsp`] and the name is empty. The weight type defaults to
+    /// [`WeightKind::Euc2d`], or to [`WeightKind::Euc3d`] if the rows contain three coordinates.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tspf::TspBuilder;
+    ///
+    /// let s = "
+    /// NODE_COORD_SECTION
+    /// 1 0 0
+    /// 2 3 4
+    /// EOF
+    /// ";
+    /// let tsp = TspBuilder::parse_str_headerless(s).unwrap();
+    /// assert_eq!(2, tsp.dim());
+    /// assert_eq!(5., tsp.weight(1, 2));
+    /// ```
+    pub fn parse_str_headerless<S>(s: S) -> Result<Tsp, ParseTspError>
+    where
+        S: AsRef<str>,
+    {
+        Self::new().read_str_headerless(s)
     }
 
     /// Parses the content of a file given from a path.
@@ -319,199 +2244,580 @@ impl TspBuilder {
     where
         P: AsRef<Path>,
     {
-        if path.as_ref().is_dir() {
-            return Err(ParseTspError::Other("Path is a directory"));
-        }
+        Self::new().read_path(path)
+    }
 
-        let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let mut lines_it = reader.lines().map(|l| l.unwrap());
-        Self::parse_it(&mut lines_it)
+    /// Parses the content of a reader, e.g. a file or a network stream.
+    ///
+    /// Unlike [`TspBuilder::parse_str`], the input does not have to be held in memory as a whole,
+    /// which matters for large instances. Like [`TspBuilder::parse_path`], lines are read one at a
+    /// time.
+    pub fn parse_reader<R>(reader: R) -> Result<Tsp, ParseTspError>
+    where
+        R: BufRead,
+    {
+        Self::new().read_reader(reader)
+    }
+
+    /// Parses a dataset whose specification part and data part are stored in separate files.
+    ///
+    /// The file ```spec``` is parsed first, followed by the data sections in the file ```data```,
+    /// e.g. an ```EDGE_WEIGHT_SECTION```. The data file may contain further specification entries,
+    /// which are added to those of the first file.
+    pub fn parse_path_with_data<P>(spec: P, data: P) -> Result<Tsp, ParseTspError>
+    where
+        P: AsRef<Path>,
+    {
+        Self::new().read_path_with_data(spec, data)
     }
 
     /// Parses each line iterator.
-    fn parse_it<I>(itr: &mut I) -> Result<Tsp, ParseTspError>
+    fn load_it<I>(mut self, itr: &mut I) -> Result<Self, ParseTspError>
+    where
+        I: Iterator,
+        <I as Iterator>::Item: AsRef<str>,
+    {
+        if self.preserve_raw {
+            let mut raw = Vec::new();
+            let mut rec = RecordingLines {
+                inner: itr,
+                raw: &mut raw,
+            }
+            .peekable();
+            self.parse_lines(&mut rec)?;
+            // Lines after EOF are not needed for parsing, but are part of the input.
+            let trailer: Vec<String> = rec.map(|l| String::from(l.as_ref())).collect();
+            self.raw_lines = Some(raw);
+            if self.capture_trailer {
+                self.trailer = trailer.join("\n").trim_end().to_string();
+            }
+        } else {
+            let mut itr = itr.peekable();
+            self.parse_lines(&mut itr)?;
+            if self.capture_trailer {
+                let trailer: Vec<String> = itr.map(|l| String::from(l.as_ref())).collect();
+                self.trailer = trailer.join("\n").trim_end().to_string();
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Parses all entries until the end of the input or ```EOF```.
+    fn parse_lines<I>(&mut self, itr: &mut Peekable<I>) -> Result<(), ParseTspError>
     where
         I: Iterator,
         <I as Iterator>::Item: AsRef<str>,
     {
-        let splitter = |s: &str| {
-            let val = s.split(':').collect::<Vec<&str>>();
-            String::from(val[1].trim())
+        // Keys and values are separated by a colon. Some near-TSPLIB formats use an equals sign
+        // instead, e.g. `DIMENSION = 52`. Only the first separator counts, so that values like
+        // comments may contain either character.
+        let splitter = |s: &str| match s.find([':', '=']) {
+            Some(idx) => String::from(s[idx + 1..].trim()),
+            None => String::new(),
         };
+        // Numeric values may be followed by an inline comment, e.g. `DIMENSION: 52 # cities`.
+        let num_splitter = |s: &str| String::from(strip_comment(&splitter(s)));
 
-        let mut builder = TspBuilder::new();
+        let spec_keys = [
+            K_NAME,
+            K_TYPE,
+            K_COMMENT,
+            K_DIM,
+            K_CAP,
+            K_SCALE,
+            K_GTSP_SETS,
+            K_VEHICLES,
+            K_WEIGHT_TYPE,
+            K_WEIGHT_FORMAT,
+            K_EDGE_FORMAT,
+            K_NODE_COORD_TYPE,
+            K_DISP_TYPE,
+        ];
 
-        while let Some(line) = itr.next() {
-            let line = line.as_ref().trim();
+        // Besides the known keywords, any entry with a separator and any upper-case word with an
+        // underscore, e.g. ```SERVICE_TIME```, is taken as a keyword.
+        let is_keyword = |s: &str| {
+            let word = s.split_whitespace().next().unwrap_or("");
+            spec_keys.iter().any(|k| s.starts_with(k))
+                || s.contains([':', '='])
+                || (word.contains('_') && word.chars().all(|c| c.is_ascii_uppercase() || c == '_'))
+                || is_eof(s)
+        };
+
+        while let Some(item) = itr.next() {
+            let mut line = item.as_ref().trim();
             if line.is_empty() {
                 continue;
             }
-            if line.starts_with("EOF") {
+            if is_eof(line) {
                 break;
             }
 
+            let joined;
+            if let Some(key) = spec_keys.iter().find(|k| line.starts_with(*k)) {
+                if !self.spec_keys.iter().any(|k| k == key) {
+                    self.spec_keys.push(key.to_string());
+                }
+
+                // Some files write the value of a keyword on the following line. A following
+                // keyword or section is never taken as a value.
+                let rest = line[key.len()..].trim_matches(|c: char| c == ':' || c == '=');
+                if rest.trim().is_empty() {
+                    while let Some(next) = itr.peek() {
+                        if !next.as_ref().trim().is_empty() {
+                            break;
+                        }
+                        itr.next();
+                    }
+
+                    if let Some(next) = itr.peek() {
+                        let next = next.as_ref().trim();
+                        if !is_keyword(next) {
+                            joined = format!("{}: {}", key, next);
+                            line = &joined;
+                            itr.next();
+                        }
+                    }
+                }
+            }
+
             if line.starts_with(K_NAME) {
-                builder.name = Some(splitter(&line));
+                self.name = Some(splitter(line));
             } else if line.starts_with(K_TYPE) {
-                builder.kind = Some(TspKind::try_from(InputWrapper(splitter(&line).as_str()))?);
-            } else if line.starts_with("COMMENT") {
+                self.kind = Some(TspKind::try_from(InputWrapper(splitter(line).as_str()))?);
+            } else if line.starts_with(K_COMMENT) {
                 // TODO: multiple-line comments?
-                builder.comment = Some(splitter(&line));
+                self.comment = Some(splitter(line));
             } else if line.starts_with(K_DIM) {
-                builder.dim = Some(splitter(&line).parse::<usize>().unwrap());
-            } else if line.starts_with("CAPACITY") {
-                builder.capacity = Some(splitter(&line).parse::<f64>().unwrap());
+                let val = num_splitter(line);
+                match val.parse::<usize>() {
+                    Ok(dim) => self.dim = Some(dim),
+                    Err(_) => {
+                        return Err(ParseTspError::InvalidInput {
+                            key: K_DIM.to_string(),
+                            val,
+                        })
+                    }
+                }
+            } else if line.starts_with(K_CAP) {
+                // Capacity is stored as a float, consistent with demands, so that variants
+                // writing e.g. `6000.0` are accepted as well.
+                let val = num_splitter(line);
+                match val.parse::<f64>() {
+                    Ok(cap) => self.capacity = Some(cap),
+                    Err(_) => {
+                        return Err(ParseTspError::InvalidInput {
+                            key: K_CAP.to_string(),
+                            val,
+                        })
+                    }
+                }
+            } else if line.starts_with(K_SCALE) {
+                let val = num_splitter(line);
+                match val.parse::<f64>() {
+                    Ok(scale) => self.scale = Some(scale),
+                    Err(_) => {
+                        return Err(ParseTspError::InvalidInput {
+                            key: K_SCALE.to_string(),
+                            val,
+                        })
+                    }
+                }
+            } else if line.starts_with(K_GTSP_SETS) {
+                let val = num_splitter(line);
+                match val.parse::<usize>() {
+                    Ok(n) => self.gtsp_sets = Some(n),
+                    Err(_) => {
+                        return Err(ParseTspError::InvalidInput {
+                            key: K_GTSP_SETS.to_string(),
+                            val,
+                        })
+                    }
+                }
+            } else if line.starts_with(K_VEHICLES) {
+                let val = num_splitter(line);
+                match val.parse::<usize>() {
+                    Ok(n) => self.vehicles = Some(n),
+                    Err(_) => {
+                        return Err(ParseTspError::InvalidInput {
+                            key: K_VEHICLES.to_string(),
+                            val,
+                        })
+                    }
+                }
             } else if line.starts_with(K_WEIGHT_TYPE) {
-                let kind = WeightKind::try_from(InputWrapper(splitter(&line).as_str()))?;
-                builder.weight_kind = Some(kind);
-                builder.coord_kind = Some(CoordKind::from(kind));
+                let kind = WeightKind::try_from(InputWrapper(splitter(line).as_str()))?;
+                self.weight_kind = Some(kind);
+                self.coord_kind = Some(CoordKind::from(kind));
             } else if line.starts_with(K_WEIGHT_FORMAT) {
-                builder.weight_format = Some(WeightFormat::try_from(InputWrapper(
-                    splitter(&line).as_str(),
+                self.weight_format = Some(WeightFormat::try_from(InputWrapper(
+                    splitter(line).as_str(),
                 ))?);
             } else if line.starts_with(K_EDGE_FORMAT) {
-                builder.edge_format = Some(EdgeFormat::try_from(InputWrapper(
-                    splitter(&line).as_str(),
-                ))?);
+                self.edge_format =
+                    Some(EdgeFormat::try_from(InputWrapper(splitter(line).as_str()))?);
             } else if line.starts_with(K_NODE_COORD_TYPE) {
-                builder.coord_kind =
-                    Some(CoordKind::try_from(InputWrapper(splitter(&line).as_str()))?);
+                self.coord_kind = Some(CoordKind::try_from(InputWrapper(splitter(line).as_str()))?);
             } else if line.starts_with(K_DISP_TYPE) {
-                builder.disp_kind = Some(DisplayKind::try_from(InputWrapper(
-                    splitter(&line).as_str(),
+                self.disp_kind = Some(DisplayKind::try_from(InputWrapper(
+                    splitter(line).as_str(),
                 ))?);
             } else if line.starts_with(K_NODE_COORD_SEC) {
-                builder.parse_node_coord_section(itr)?;
+                self.parse_node_coord_section(itr)?;
             } else if line.starts_with("DEPOT_SECTION") {
-                builder.parse_depot_section(itr)?;
+                self.parse_depot_section(itr)?;
             } else if line.starts_with("DEMAND_SECTION") {
-                builder.parse_demand_section(itr)?;
+                self.parse_demand_section(itr)?;
+            } else if line.starts_with(K_TIME_WINDOW_SEC) {
+                self.parse_time_window_section(itr)?;
+            } else if line.starts_with(K_GTSP_SET_SEC) {
+                self.parse_gtsp_set_section(itr)?;
             } else if line.starts_with("EDGE_DATA_SECTION") {
-                builder.parse_edge_data_section(itr)?;
+                self.parse_edge_data_section(itr)?;
             } else if line.starts_with("FIXED_EDGES_SECTION") {
-                builder.parse_fixed_edges_section(itr)?;
-            } else if line.starts_with("DISPLAY_DATA_SECTION") {
-                builder.parse_display_data_section(itr)?;
+                self.parse_fixed_edges_section(itr)?;
+            } else if line.starts_with(K_DISP_SEC) {
+                self.parse_display_data_section(itr)?;
             } else if line.starts_with(K_TOUR_SEC) {
-                builder.parse_tour_section(itr)?;
+                self.parse_tour_section(itr)?;
             } else if line.starts_with(K_EDGE_WEIGHT_SEC) {
-                builder.parse_edge_weight_section(itr)?;
+                self.parse_edge_weight_section(itr)?;
             } else {
                 return Err(ParseTspError::InvalidEntry(String::from(line)));
             }
         }
 
-        builder.build()
+        Ok(())
+    }
+
+    /// Parse the block `NODE_COORD_SECTION`.
+    ///
+    /// If the coordinate type is not known, e.g. for explicit edge weights with coordinates for
+    /// display purposes, it is inferred from the number of values in the first row.
+    fn parse_node_coord_section<I>(
+        &mut self,
+        lines_it: &mut Peekable<I>,
+    ) -> Result<(), ParseTspError>
+    where
+        I: Iterator,
+        <I as Iterator>::Item: AsRef<str>,
+    {
+        if let (None, Some(wk)) = (self.weight_kind, self.default_weight_kind) {
+            self.weight_kind = Some(wk);
+            self.warnings.push(format!(
+                "{}: missing, using {}",
+                K_WEIGHT_TYPE,
+                wk.tsp_str()
+            ));
+        }
+        self.validate_spec()?;
+
+        if let None | Some(CoordKind::NoCoord) | Some(CoordKind::Undefined) = self.coord_kind {
+            // The type is inferred from the leading numbers of the first row, so blank lines and
+            // comments before it are skipped and labels after the coordinates are not counted.
+            while let Some(line) = lines_it.peek() {
+                let line = line.as_ref().trim();
+                if !line.is_empty() && !line.starts_with(K_COMMENT) {
+                    break;
+                }
+                if let Some(line) = lines_it.next() {
+                    self.skip_comment(K_NODE_COORD_SEC, line.as_ref());
+                }
+            }
+            let n_tokens = lines_it
+                .peek()
+                .map(|l| {
+                    self.decimal_point(l.as_ref())
+                        .split_whitespace()
+                        .take_while(|t| t.parse::<f64>().is_ok())
+                        .count()
+                })
+                .unwrap_or(0)
+                .saturating_sub(usize::from(!self.implicit_ids));
+            self.coord_kind = Some(if n_tokens == 3 {
+                CoordKind::Coord3d
+            } else {
+                CoordKind::Coord2d
+            });
+        }
+
+        let n_coords = match self.coord_kind.unwrap() {
+            CoordKind::Coord2d => 2,
+            CoordKind::Coord3d => 3,
+            CoordKind::NoCoord | CoordKind::Undefined => {
+                unreachable!("the coordinate type is inferred above")
+            }
+        };
+
+        let mut count = 0;
+        let dim = self.dim.unwrap();
+        let mut dta = HashMap::with_capacity(dim);
+
+        while count < dim {
+            let line = match lines_it.next() {
+                Some(line) if !is_eof(line.as_ref().trim()) => line,
+                _ => {
+                    return Err(ParseTspError::InvalidEntry(format!(
+                        "{}: expected {} nodes, found {}",
+                        K_NODE_COORD_SEC, dim, count
+                    )))
+                }
+            };
+            if line.as_ref().trim().is_empty() || self.skip_comment(K_NODE_COORD_SEC, line.as_ref())
+            {
+                continue;
+            }
+            let line = self.decimal_point(line.as_ref());
+            let v = line.split_whitespace().collect::<Vec<&str>>();
+            let (id, coords) = if self.implicit_ids {
+                (count + 1, &v[..])
+            } else {
+                (parse_node_id(v[0])?, &v[1..])
+            };
+            if coords.len() < n_coords {
+                return Err(ParseTspError::InvalidEntry(String::from(line.trim())));
+            }
+            let pos = coords[..n_coords]
+                .iter()
+                .map(|x| parse_value(K_NODE_COORD_SEC, x))
+                .collect::<Result<Vec<f64>, _>>()?;
+            let pt = Point::new(id, pos);
+            // Tokens after the coordinates, e.g. city names, are kept as a label.
+            let n_used = usize::from(!self.implicit_ids) + pt.pos.len();
+            if v.len() > n_used {
+                self.warnings.push(format!(
+                    "{}: {} extra token(s) stored as label of node {}",
+                    K_NODE_COORD_SEC,
+                    v.len() - n_used,
+                    pt.id
+                ));
+                self.labels
+                    .get_or_insert_with(HashMap::new)
+                    .insert(pt.id, v[n_used..].join(" "));
+            }
+            dta.insert(pt.id, pt);
+            count += 1;
+        }
+
+        self.coords = Some(dta);
+
+        Ok(())
+    }
+
+    /// Parse the block `DEPOT_SECTION`.
+    fn parse_depot_section<I>(&mut self, lines_it: &mut Peekable<I>) -> Result<(), ParseTspError>
+    where
+        I: Iterator,
+        <I as Iterator>::Item: AsRef<str>,
+    {
+        self.validate_spec()?;
+
+        let mut dta = HashSet::new();
+        let mut order = Vec::new();
+
+        'section: loop {
+            let line = lines_it
+                .next()
+                .ok_or(ParseTspError::Other("Unexpected end of DEPOT_SECTION"))?;
+            if self.skip_comment("DEPOT_SECTION", line.as_ref()) {
+                continue;
+            }
+
+            for token in line.as_ref().split_whitespace() {
+                if token == "-1" {
+                    break 'section;
+                }
+                let id = parse_value("DEPOT_SECTION", token)?;
+                if dta.insert(id) {
+                    order.push(id);
+                }
+            }
+        }
+
+        // Some files repeat the terminator.
+        while lines_it.next_if(|l| l.as_ref().trim() == "-1").is_some() {}
+
+        self.depots = Some(dta);
+        self.depot_order = order;
+
+        Ok(())
+    }
+
+    /// Parse the block `DEMAND_SECTION`.
+    fn parse_demand_section<I>(&mut self, lines_it: &mut I) -> Result<(), ParseTspError>
+    where
+        I: Iterator,
+        <I as Iterator>::Item: AsRef<str>,
+    {
+        self.validate_spec()?;
+
+        let mut dta = HashMap::new();
+        let mut pd = HashMap::new();
+
+        let mut count = 0;
+        while count < self.dim.unwrap() {
+            let line = lines_it
+                .next()
+                .ok_or(ParseTspError::Other("Unexpected end of DEMAND_SECTION"))?;
+            // Blank lines are not counted, so that the next section is not consumed.
+            if line.as_ref().trim().is_empty() || self.skip_comment("DEMAND_SECTION", line.as_ref())
+            {
+                continue;
+            }
+            let line = self.decimal_point(line.as_ref());
+            let mut it = line.split_whitespace();
+            match (it.next(), it.next(), it.next()) {
+                (Some(id), Some(p), Some(d)) => {
+                    pd.insert(
+                        parse_value("DEMAND_SECTION", id)?,
+                        (
+                            parse_value("DEMAND_SECTION", p)?,
+                            parse_value("DEMAND_SECTION", d)?,
+                        ),
+                    );
+                }
+                (Some(id), Some(de), None) => {
+                    dta.insert(
+                        parse_value("DEMAND_SECTION", id)?,
+                        parse_value("DEMAND_SECTION", de)?,
+                    );
+                }
+                _ => {}
+            }
+            count += 1;
+        }
+
+        self.demands = Some(dta);
+        if !pd.is_empty() {
+            self.pickup_delivery = Some(pd);
+        }
+
+        Ok(())
     }
 
-    /// Parse the block `NODE_COORD_SECTION`.
-    fn parse_node_coord_section<I>(&mut self, lines_it: &mut I) -> Result<(), ParseTspError>
+    /// Parses ```TIME_WINDOW_SECTION```.
+    fn parse_time_window_section<I>(&mut self, lines_it: &mut I) -> Result<(), ParseTspError>
     where
         I: Iterator,
         <I as Iterator>::Item: AsRef<str>,
     {
         self.validate_spec()?;
 
-        let func: Box<dyn Fn(&Vec<&str>) -> Point> = match &self.coord_kind.unwrap() {
-            CoordKind::Coord2d => {
-                let f = |v: &Vec<&str>| {
-                    Point::new2(
-                        v[0].parse::<usize>().unwrap(),
-                        v[1].parse::<f64>().unwrap(),
-                        v[2].parse::<f64>().unwrap(),
-                    )
-                };
-                Box::new(f)
-            }
-            CoordKind::Coord3d => {
-                let f = |v: &Vec<&str>| {
-                    Point::new3(
-                        v[0].parse::<usize>().unwrap(),
-                        v[1].parse::<f64>().unwrap(),
-                        v[2].parse::<f64>().unwrap(),
-                        v[3].parse::<f64>().unwrap(),
-                    )
-                };
-                Box::new(f)
-            }
-            CoordKind::NoCoord | CoordKind::Undefined => {
-                unimplemented!()
-            }
-        };
+        let mut dta = HashMap::new();
 
-        let mut count = 0;
         let dim = self.dim.unwrap();
-        let mut dta = HashMap::with_capacity(dim);
-
+        let mut count = 0;
         while count < dim {
-            // TODO: replace unwrap()
-            let line = lines_it.next().unwrap();
-            let pt = func(
-                &line
-                    .as_ref()
-                    .trim()
-                    .split_whitespace()
-                    .collect::<Vec<&str>>(),
-            );
-            dta.insert(pt.id, pt);
+            let line = match lines_it.next() {
+                Some(line) if !is_eof(line.as_ref().trim()) => line,
+                _ => {
+                    return Err(ParseTspError::InvalidEntry(format!(
+                        "{}: expected {} nodes, found {}",
+                        K_TIME_WINDOW_SEC, dim, count
+                    )))
+                }
+            };
+            // Blank lines are not counted, so that the next section is not consumed.
+            if line.as_ref().trim().is_empty()
+                || self.skip_comment(K_TIME_WINDOW_SEC, line.as_ref())
+            {
+                continue;
+            }
+            let line = self.decimal_point(line.as_ref());
+            let mut it = line.split_whitespace();
+            match (it.next(), it.next(), it.next()) {
+                (Some(id), Some(e), Some(l)) => {
+                    dta.insert(
+                        parse_value(K_TIME_WINDOW_SEC, id)?,
+                        (
+                            parse_value(K_TIME_WINDOW_SEC, e)?,
+                            parse_value(K_TIME_WINDOW_SEC, l)?,
+                        ),
+                    );
+                }
+                _ => return Err(ParseTspError::InvalidEntry(String::from(line.trim()))),
+            }
             count += 1;
         }
 
-        self.coords = Some(dta);
+        self.time_windows = Some(dta);
 
         Ok(())
     }
 
-    /// Parse the block `DEPOT_SECTION`.
-    fn parse_depot_section<I>(&mut self, lines_it: &mut I) -> Result<(), ParseTspError>
+    /// Parses ```GTSP_SET_SECTION```.
+    ///
+    /// Each cluster is given as its id followed by the ids of its nodes and terminated by ```-1```,
+    /// possibly spanning several lines. If ```GTSP_SETS``` is not given, clusters are read until
+    /// a line that does not start with a digit.
+    fn parse_gtsp_set_section<I>(&mut self, lines_it: &mut Peekable<I>) -> Result<(), ParseTspError>
     where
         I: Iterator,
         <I as Iterator>::Item: AsRef<str>,
     {
         self.validate_spec()?;
 
-        let mut dta = HashSet::new();
+        let mut dta: Vec<(usize, Vec<usize>)> = Vec::with_capacity(self.gtsp_sets.unwrap_or(0));
+        let mut current: Option<(usize, Vec<usize>)> = None;
 
-        loop {
-            let line = lines_it.next().unwrap();
-            if line.as_ref().trim().starts_with("-1") {
+        while let Some(line) = lines_it.peek() {
+            let line = line.as_ref().trim();
+            if matches!(self.gtsp_sets, Some(n) if dta.len() >= n) {
                 break;
             }
+            match line.chars().next() {
+                Some(c) if c.is_ascii_digit() || c == '-' => {}
+                Some(_) => break,
+                None => {
+                    lines_it.next();
+                    continue;
+                }
+            }
 
-            dta.insert(line.as_ref().trim().parse::<usize>().unwrap());
-        }
-
-        self.depots = Some(dta);
-
-        Ok(())
-    }
+            for token in line.split_whitespace() {
+                if token == "-1" {
+                    if let Some(cluster) = current.take() {
+                        dta.push(cluster);
+                    }
+                    continue;
+                }
 
-    /// Parse the block `DEMAND_SECTION`.
-    fn parse_demand_section<I>(&mut self, lines_it: &mut I) -> Result<(), ParseTspError>
-    where
-        I: Iterator,
-        <I as Iterator>::Item: AsRef<str>,
-    {
-        self.validate_spec()?;
+                let id = parse_value(K_GTSP_SET_SEC, token)?;
+                match current.as_mut() {
+                    Some((_, nodes)) => nodes.push(id),
+                    None => current = Some((id, Vec::new())),
+                }
+            }
+            lines_it.next();
+        }
 
-        let mut dta = HashMap::new();
+        if let Some(cluster) = current {
+            dta.push(cluster);
+        }
 
-        for _ in 0..self.dim.unwrap() {
-            let line = lines_it.next().unwrap();
-            let mut it = line.as_ref().trim().split_whitespace();
-            if let (Some(id), Some(de)) = (it.next(), it.next()) {
-                dta.insert(id.parse::<usize>().unwrap(), de.parse::<f64>().unwrap());
+        if let Some(n) = self.gtsp_sets {
+            if n != dta.len() {
+                self.warnings.push(format!(
+                    "{}: {} clusters declared but {} found",
+                    K_GTSP_SET_SEC,
+                    n,
+                    dta.len()
+                ));
             }
         }
 
-        self.demands = Some(dta);
+        dta.sort_by_key(|(id, _)| *id);
+        self.clusters = Some(dta.into_iter().map(|(_, nodes)| nodes).collect());
 
         Ok(())
     }
 
     /// Parses the ```EDGE_DATA_SECTION```.
-    fn parse_edge_data_section<I>(&mut self, lines_it: &mut I) -> Result<(), ParseTspError>
+    fn parse_edge_data_section<I>(
+        &mut self,
+        lines_it: &mut Peekable<I>,
+    ) -> Result<(), ParseTspError>
     where
         I: Iterator,
         <I as Iterator>::Item: AsRef<str>,
@@ -520,15 +2826,19 @@ impl TspBuilder {
 
         match self.edge_format.as_mut().unwrap() {
             EdgeFormat::EdgeList(v) => {
-                loop {
-                    let line = lines_it.next().unwrap();
+                // The section ends with -1, but some files omit it and end with EOF instead,
+                // which is left for the caller.
+                while let Some(line) = lines_it.next_if(|l| !is_eof(l.as_ref().trim())) {
                     if line.as_ref().trim().starts_with("-1") {
                         break;
                     }
 
-                    let mut it = line.as_ref().trim().split_whitespace();
+                    let mut it = line.as_ref().split_whitespace();
                     if let (Some(f), Some(l)) = (it.next(), it.next()) {
-                        dta.push((f.parse::<usize>().unwrap(), l.parse::<usize>().unwrap()));
+                        dta.push((
+                            parse_value("EDGE_DATA_SECTION", f)?,
+                            parse_value("EDGE_DATA_SECTION", l)?,
+                        ));
                     }
                 }
 
@@ -555,10 +2865,16 @@ impl TspBuilder {
             if line.as_ref().trim().starts_with("-1") {
                 break;
             }
+            if self.skip_comment("FIXED_EDGES_SECTION", line.as_ref()) {
+                continue;
+            }
 
-            let mut it = line.as_ref().trim().split_whitespace();
+            let mut it = line.as_ref().split_whitespace();
             if let (Some(f), Some(l)) = (it.next(), it.next()) {
-                dta.push((f.parse::<usize>().unwrap(), l.parse::<usize>().unwrap()));
+                dta.push((
+                    parse_value("FIXED_EDGES_SECTION", f)?,
+                    parse_value("FIXED_EDGES_SECTION", l)?,
+                ));
             }
         }
 
@@ -568,7 +2884,11 @@ impl TspBuilder {
     }
 
     /// Parses ```TOUR_SECTION```.
-    fn parse_tour_section<I>(&mut self, lines_it: &mut I) -> Result<(), ParseTspError>
+    ///
+    /// Node ids may be separated by whitespace or commas, and each tour is terminated by ```-1```.
+    /// The section ends with an additional ```-1``` or at the first line that does not start with
+    /// a number.
+    fn parse_tour_section<I>(&mut self, lines_it: &mut Peekable<I>) -> Result<(), ParseTspError>
     where
         I: Iterator,
         <I as Iterator>::Item: AsRef<str>,
@@ -576,46 +2896,42 @@ impl TspBuilder {
         self.validate_spec()?;
         let mut dta = Vec::new();
         let mut v = Vec::new();
+        let mut done = false;
 
-        // Naive implementation.
-        loop {
-            let line = lines_it.next().unwrap();
-            let s = line.as_ref().trim();
-
-            if s.starts_with("-1") {
-                let tmp = v.drain(0..).collect();
-                dta.push(tmp);
+        while let Some(line) = lines_it.peek() {
+            let line = line.as_ref().trim();
+            match line.chars().next() {
+                Some(c) if c.is_ascii_digit() || c == '-' => {}
+                Some(_) => break,
+                None => {
+                    lines_it.next();
+                    continue;
+                }
+            }
 
-                match lines_it.peekable().peek() {
-                    Some(peek) => {
-                        let s = peek.as_ref().trim();
-                        if s.starts_with("-1") {
-                            break;
-                        }
-                        let ch = s.chars().next().unwrap();
-                        if ch.is_digit(10) {
-                            v = Vec::new();
-                            v.append(
-                                &mut s
-                                    .split_whitespace()
-                                    .map(|s| s.parse::<usize>().unwrap())
-                                    .collect(),
-                            );
-                        } else {
-                            break;
-                        }
+            for token in line
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|t| !t.is_empty())
+            {
+                if token == "-1" {
+                    if v.is_empty() {
+                        done = true;
+                        break;
                     }
-                    None => break,
-                };
-                continue;
+                    dta.push(std::mem::take(&mut v));
+                } else {
+                    v.push(parse_value(K_TOUR_SEC, token)?);
+                }
             }
 
-            v.append(
-                &mut s
-                    .split_whitespace()
-                    .map(|s| s.parse::<usize>().unwrap())
-                    .collect(),
-            );
+            lines_it.next();
+            if done {
+                break;
+            }
+        }
+
+        if !v.is_empty() {
+            dta.push(v);
         }
 
         self.tours = Some(dta);
@@ -633,49 +2949,71 @@ impl TspBuilder {
         let dim = self.dim.unwrap();
 
         // TODO: check memory consumption for large files.
-        let (len_vec, cnt, it): (usize, usize, Box<dyn Iterator<Item = usize>>) =
-            match self.weight_format.unwrap() {
-                WeightFormat::Function => (0, 0, Box::new(std::iter::empty::<usize>())),
-                WeightFormat::FullMatrix => {
-                    (dim, dim * dim, Box::new(std::iter::repeat(dim).take(dim)))
-                }
-                WeightFormat::UpperRow | WeightFormat::LowerCol => {
-                    (dim - 1, dim * (dim - 1) / 2, Box::new((1..dim).rev()))
-                }
-                WeightFormat::LowerRow | WeightFormat::UpperCol => {
-                    (dim - 1, dim * (dim - 1) / 2, Box::new(1..dim))
-                }
-                WeightFormat::UpperDiagRow | WeightFormat::LowerDiagCol => {
-                    (dim, dim * (dim + 1) / 2, Box::new((1..=dim).rev()))
-                }
-                WeightFormat::LowerDiagRow | WeightFormat::UpperDiagCol => {
-                    (dim, dim * (dim + 1) / 2, Box::new(1..=dim))
-                }
-                WeightFormat::Undefined => (0, 0, Box::new(std::iter::empty::<usize>())),
-            };
+        let row_lens = self.weight_format.unwrap().row_lens(dim);
+        let cnt = row_lens.iter().sum();
 
-        let mut dta = Vec::with_capacity(len_vec);
+        let mut dta = Vec::with_capacity(row_lens.len());
         let mut v = Vec::with_capacity(cnt);
 
+        // Values are accumulated regardless of line breaks and column widths, since rows are
+        // often wrapped or separated by tabs and padding in files from other sources. The number
+        // of values per line is only used to locate a short row in case of missing values.
+        let mut line_lens = Vec::new();
         while v.len() < cnt {
-            let line = lines_it.next().unwrap();
-            let mut tmp: Vec<f64> = line
-                .as_ref()
-                .trim()
-                .split_whitespace()
-                .map(|s| s.parse::<f64>().unwrap())
-                .collect();
+            let line = match lines_it.next() {
+                Some(line) => line,
+                None => {
+                    return Err(short_weight_row(&row_lens, &line_lens).unwrap_or(
+                        ParseTspError::Other("Unexpected end of EDGE_WEIGHT_SECTION"),
+                    ))
+                }
+            };
+            if self.skip_comment(K_EDGE_WEIGHT_SEC, line.as_ref()) {
+                continue;
+            }
+            let trimmed = line.as_ref().trim();
+            if is_eof(trimmed) || trimmed.contains("_SECTION") {
+                return Err(short_weight_row(&row_lens, &line_lens).unwrap_or_else(|| {
+                    ParseTspError::InvalidInput {
+                        key: K_EDGE_WEIGHT_SEC.to_string(),
+                        val: format!("expected {} values, found {}", cnt, v.len()),
+                    }
+                }));
+            }
 
-            v.append(&mut tmp);
+            let n = v.len();
+            for token in self.decimal_point(line.as_ref()).split_whitespace() {
+                match token.parse::<f64>() {
+                    Ok(w) => v.push(w),
+                    Err(_) => {
+                        return Err(ParseTspError::InvalidInput {
+                            key: K_EDGE_WEIGHT_SEC.to_string(),
+                            val: token.to_string(),
+                        })
+                    }
+                }
+            }
+            if v.len() > n {
+                line_lens.push(v.len() - n);
+            }
         }
 
         // The SOP files from TSPLIB has an extra line containing dimension in this section,
-        // which does not follow the specification.
-        if v.len() == dim + 1 {
+        // which does not follow the specification. This leading value is detected from the total
+        // count, which also works if the whole section is written on a single line.
+        if v.len() == cnt + 1 && v[0] == dim as f64 {
             v.remove(0);
         }
 
-        for len_row in it {
+        // Any remaining surplus means that rows and values are misaligned.
+        if v.len() != cnt {
+            return Err(ParseTspError::InvalidInput {
+                key: K_EDGE_WEIGHT_SEC.to_string(),
+                val: format!("expected {} values, found {}", cnt, v.len()),
+            });
+        }
+
+        for len_row in row_lens {
             dta.push(v.drain(0..len_row).collect());
         }
 
@@ -684,30 +3022,70 @@ impl TspBuilder {
         Ok(())
     }
 
-    fn parse_display_data_section<I>(&mut self, lines_it: &mut I) -> Result<(), ParseTspError>
+    /// Parses ```DISPLAY_DATA_SECTION```.
+    ///
+    /// If the dimension is not given, as it is allowed for [`TspKind::Tour`], rows are read until
+    /// ```-1```, the end of the input or a line that does not start with a node id.
+    fn parse_display_data_section<I>(
+        &mut self,
+        lines_it: &mut Peekable<I>,
+    ) -> Result<(), ParseTspError>
     where
         I: Iterator,
         <I as Iterator>::Item: AsRef<str>,
     {
         self.validate_spec()?;
-        let dim = self.dim.unwrap();
-        let mut dta = Vec::with_capacity(dim);
+        let mut dta = Vec::with_capacity(self.dim.unwrap_or(0));
 
-        let mut count = 0;
-        while count < dim {
-            let line = lines_it.next().unwrap();
-            let v = line
-                .as_ref()
-                .trim()
-                .split_whitespace()
-                .collect::<Vec<&str>>();
-            dta.push(Point::new2(
-                v[0].parse::<usize>().unwrap(),
-                v[1].parse::<f64>().unwrap(),
-                v[2].parse::<f64>().unwrap(),
-            ));
+        let parse_row = |line: &str| -> Result<Point, ParseTspError> {
+            match line.split_whitespace().collect::<Vec<&str>>()[..] {
+                [id, x, y, ..] => Ok(Point::new2(
+                    parse_node_id(id)?,
+                    parse_value(K_DISP_SEC, x)?,
+                    parse_value(K_DISP_SEC, y)?,
+                )),
+                _ => Err(ParseTspError::InvalidEntry(String::from(line.trim()))),
+            }
+        };
 
-            count += 1;
+        match self.dim {
+            Some(dim) => {
+                let mut count = 0;
+                while count < dim {
+                    let line = match lines_it.next() {
+                        Some(line) if !is_eof(line.as_ref().trim()) => line,
+                        _ => {
+                            return Err(ParseTspError::InvalidEntry(format!(
+                                "{}: expected {} nodes, found {}",
+                                K_DISP_SEC, dim, count
+                            )))
+                        }
+                    };
+                    if line.as_ref().trim().is_empty()
+                        || self.skip_comment(K_DISP_SEC, line.as_ref())
+                    {
+                        continue;
+                    }
+                    dta.push(parse_row(line.as_ref())?);
+                    count += 1;
+                }
+            }
+            None => {
+                while let Some(line) = lines_it.peek() {
+                    let line = line.as_ref().trim();
+                    if line.starts_with("-1") {
+                        lines_it.next();
+                        break;
+                    }
+
+                    match line.chars().next() {
+                        Some(c) if c.is_ascii_digit() => dta.push(parse_row(line)?),
+                        Some(_) => break,
+                        None => {}
+                    }
+                    lines_it.next();
+                }
+            }
         }
 
         self.disp_coords = Some(dta);
@@ -715,6 +3093,30 @@ impl TspBuilder {
         Ok(())
     }
 
+    /// Checks whether a line inside a data section is a ```COMMENT``` entry.
+    ///
+    /// Hand-edited files occasionally contain comments between data rows. Such lines are skipped
+    /// and reported as warnings instead of being parsed as data.
+    fn skip_comment(&mut self, section: &str, line: &str) -> bool {
+        let line = line.trim();
+        if line.starts_with(K_COMMENT) {
+            self.warnings
+                .push(format!("{}: comment ignored: {}", section, line));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Replaces decimal commas with points if requested with [`TspBuilder::decimal_comma`].
+    fn decimal_point<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if self.decimal_comma {
+            Cow::Owned(line.replace(',', "."))
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+
     /// Validates the specification part.
     fn validate_spec(&self) -> Result<(), ParseTspError> {
         if self.name.is_none() {
@@ -724,7 +3126,7 @@ impl TspBuilder {
         match self.kind {
             Some(kind) => {
                 match kind {
-                    TspKind::Tsp | TspKind::Atsp | TspKind::Cvrp | TspKind::Sop => {
+                    TspKind::Tsp | TspKind::Atsp | TspKind::Cvrp | TspKind::Gtsp | TspKind::Sop => {
                         match self.weight_kind {
                             Some(wk) => {
                                 if wk == WeightKind::Undefined {
@@ -772,12 +3174,23 @@ impl TspBuilder {
         Ok(())
     }
 
+    /// Checks whether the edge weights are declared as explicit but given by a function.
+    ///
+    /// Such a combination does not come with an ```EDGE_WEIGHT_SECTION```.
+    fn is_explicit_function(&self) -> bool {
+        self.weight_kind == Some(WeightKind::Explicit)
+            && self.weight_format == Some(WeightFormat::Function)
+    }
+
     /// Validates the data part.
     fn validate_data(&self) -> Result<(), ParseTspError> {
         match self.kind.unwrap() {
-            TspKind::Tsp | TspKind::Atsp | TspKind::Cvrp => match self.weight_kind.unwrap() {
+            TspKind::Tsp | TspKind::Atsp | TspKind::Cvrp | TspKind::Gtsp => match self
+                .weight_kind
+                .unwrap()
+            {
                 WeightKind::Explicit => {
-                    if self.edge_weights.is_none() {
+                    if self.edge_weights.is_none() && !self.is_explicit_function() {
                         return Err(ParseTspError::MissingEntry(String::from(K_EDGE_WEIGHT_SEC)));
                     }
                 }
@@ -797,10 +3210,10 @@ impl TspBuilder {
             TspKind::Undefined => {}
         }
 
-        if self.weight_kind.is_some() {
-            match self.weight_kind.unwrap() {
+        if let Some(wk) = self.weight_kind {
+            match wk {
                 WeightKind::Explicit => {
-                    if self.edge_weights.is_none() {
+                    if self.edge_weights.is_none() && !self.is_explicit_function() {
                         return Err(ParseTspError::MissingEntry(String::from(K_EDGE_WEIGHT_SEC)));
                     }
                 }
@@ -812,39 +3225,263 @@ impl TspBuilder {
             }
         }
 
+        if self.kind == Some(TspKind::Cvrp) && self.demands.is_none() {
+            return Err(ParseTspError::MissingEntry(String::from("DEMAND_SECTION")));
+        }
+
+        // Fixed edges must refer to existing nodes, which are the nodes with coordinates if
+        // given, or 1..=dim otherwise.
+        if let Some(edges) = &self.fixed_edges {
+            let dim = self.dim.unwrap_or(0);
+            let is_node = |id: &usize| match &self.coords {
+                Some(coords) => coords.contains_key(id),
+                None => (1..=dim).contains(id),
+            };
+
+            if let Some((a, b)) = edges.iter().find(|(a, b)| !is_node(a) || !is_node(b)) {
+                return Err(ParseTspError::InvalidInput {
+                    key: String::from("FIXED_EDGES_SECTION"),
+                    val: format!("{} {}", a, b),
+                });
+            }
+        }
+
         Ok(())
     }
 
+    /// Collects anomalies in the data part that do not prevent the construction of a [`Tsp`]
+    /// object.
+    fn check_data(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let dim = self.dim.unwrap_or(0);
+
+        if let (Some(kind), Some(_)) = (self.kind, self.capacity) {
+            if kind != TspKind::Cvrp {
+                warnings.push(format!("{}: ignored for type {}", K_CAP, kind.tsp_str()));
+            }
+        }
+
+        if let Some(demands) = &self.demands {
+            let mut ids: Vec<&usize> = demands.keys().collect();
+            ids.sort_unstable();
+
+            for id in ids {
+                if !(1..=dim).contains(id) {
+                    warnings.push(format!(
+                        "DEMAND_SECTION: demand given for unknown node {}",
+                        id
+                    ));
+                }
+            }
+
+            if let Some(depots) = &self.depots {
+                let mut depots: Vec<&usize> = depots.iter().collect();
+                depots.sort_unstable();
+
+                for id in depots {
+                    match demands.get(id) {
+                        Some(d) if *d != 0. => warnings.push(format!(
+                            "DEMAND_SECTION: depot {} has non-zero demand {}",
+                            id, d
+                        )),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        if let Some(disp_coords) = &self.disp_coords {
+            if self.dim.is_some() && disp_coords.len() != dim {
+                warnings.push(format!(
+                    "DISPLAY_DATA_SECTION: {} rows given for dimension {}",
+                    disp_coords.len(),
+                    dim
+                ));
+            }
+        }
+
+        warnings
+    }
+
     /// Validates the inputs and constructs a [`Tsp`] object if the validation is successful.
     /// Otherwise, returns an error [`ParseTspError`].
     pub fn build(self) -> Result<Tsp, ParseTspError> {
+        self.build_with_warnings().map(|(tsp, _)| tsp)
+    }
+
+    /// Validates the inputs and constructs a [`Tsp`] object together with a list of non-fatal
+    /// anomalies found in the inputs, e.g. ignored tokens or a depot with a non-zero demand.
+    ///
+    /// Returns an error [`ParseTspError`] if the validation fails.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use tspf::TspBuilder;
+    /// use std::path::Path;
+    ///
+    /// let path = Path::new("./tests/data/berlin52.tsp");
+    /// let (tsp, warnings) = TspBuilder::new()
+    ///     .load_path(path)
+    ///     .and_then(|b| b.build_with_warnings())
+    ///     .unwrap();
+    /// assert_eq!(52, tsp.dim());
+    /// assert!(warnings.is_empty());
+    /// ```
+    pub fn build_with_warnings(mut self) -> Result<(Tsp, Vec<String>), ParseTspError> {
         self.validate_spec()?;
         self.validate_data()?;
 
+        let mut warnings = std::mem::take(&mut self.warnings);
+        warnings.append(&mut self.check_data());
+
+        if let (true, Some(scale), Some(coords)) =
+            (self.apply_scale, self.scale, self.coords.as_mut())
+        {
+            for pt in coords.values_mut() {
+                pt.pos.iter_mut().for_each(|x| *x *= scale);
+            }
+        }
+
+        let present_sections = SectionFlags {
+            node_coords: self.coords.is_some(),
+            demands: self.demands.is_some(),
+            depots: self.depots.is_some(),
+            edge_weights: self.edge_weights.is_some(),
+            display: self.disp_coords.is_some(),
+            fixed_edges: self.fixed_edges.is_some(),
+            tours: self.tours.is_some(),
+            time_windows: self.time_windows.is_some(),
+            clusters: self.clusters.is_some(),
+        };
+
         let tsp = Tsp {
             name: self.name.unwrap(),
             kind: self.kind.unwrap(),
-            comment: self.comment.unwrap_or_else(String::new),
+            comment: self.comment.unwrap_or_default(),
             dim: self.dim.unwrap_or(0),
             capacity: self.capacity.unwrap_or(0.),
+            scale_factor: self.scale,
+            vehicles: self.vehicles,
             weight_kind: self.weight_kind.unwrap_or(WeightKind::Undefined),
             weight_format: self.weight_format.unwrap_or(WeightFormat::Undefined),
             edge_format: self.edge_format.unwrap_or(EdgeFormat::Undefined),
             coord_kind: self.coord_kind.unwrap_or(CoordKind::Undefined),
             disp_kind: self.disp_kind.unwrap_or(DisplayKind::Undefined),
             node_coords: self.coords.unwrap_or_else(|| HashMap::with_capacity(0)),
+            node_labels: self.labels.unwrap_or_else(|| HashMap::with_capacity(0)),
             demands: self.demands.unwrap_or_else(|| HashMap::with_capacity(0)),
+            pickup_delivery: self
+                .pickup_delivery
+                .unwrap_or_else(|| HashMap::with_capacity(0)),
             depots: self.depots.unwrap_or_else(|| HashSet::with_capacity(0)),
+            depot_order: self.depot_order,
             edge_weights: self.edge_weights.unwrap_or_else(|| Vec::with_capacity(0)),
             disp_coords: self.disp_coords.unwrap_or_else(|| Vec::with_capacity(0)),
             fixed_edges: self.fixed_edges.unwrap_or_else(|| Vec::with_capacity(0)),
             tours: self.tours.unwrap_or_else(|| Vec::with_capacity(0)),
+            time_windows: self
+                .time_windows
+                .unwrap_or_else(|| HashMap::with_capacity(0)),
+            clusters: self.clusters.unwrap_or_else(|| Vec::with_capacity(0)),
+            spec_key_order: self.spec_keys,
+            present_sections,
+            trailer: self.trailer,
+            raw_lines: self.raw_lines,
+            geo_lon_lat: self.geo_lon_lat,
+            matrix_indexing: self.matrix_indexing,
+            rounding_policy: self.rounding_policy,
+            weight_bounds: OnceCell::new(),
         };
 
-        Ok(tsp)
+        Ok((tsp, warnings))
+    }
+}
+
+/// Locates a short row of ```EDGE_WEIGHT_SECTION``` from the number of values in each line.
+///
+/// A row can only be located if the lines correspond to the rows of the matrix and exactly one of
+/// them has a different length, since rows may otherwise be wrapped arbitrarily.
+fn short_weight_row(row_lens: &[usize], line_lens: &[usize]) -> Option<ParseTspError> {
+    if line_lens.len() != row_lens.len() {
+        return None;
+    }
+
+    let mut mismatches = row_lens
+        .iter()
+        .zip(line_lens)
+        .enumerate()
+        .filter(|(_, (r, l))| r != l);
+    match (mismatches.next(), mismatches.next()) {
+        (Some((idx, (expected, found))), None) => Some(ParseTspError::InvalidInput {
+            key: K_EDGE_WEIGHT_SEC.to_string(),
+            val: format!("row {} has {} values, expected {}", idx, found, expected),
+        }),
+        _ => None,
+    }
+}
+
+/// Parses a node id, which must be a positive integer.
+fn parse_node_id(token: &str) -> Result<usize, ParseTspError> {
+    match token.parse::<usize>() {
+        Ok(id) if id > 0 && token.bytes().all(|b| b.is_ascii_digit()) => Ok(id),
+        _ => Err(ParseTspError::InvalidInput {
+            key: String::from("node id"),
+            val: String::from(token),
+        }),
+    }
+}
+
+/// Parses a value of a section, reporting the section and the token if it is not a number.
+fn parse_value<T: FromStr>(key: &str, token: &str) -> Result<T, ParseTspError> {
+    token.parse().map_err(|_| ParseTspError::InvalidInput {
+        key: String::from(key),
+        val: String::from(token),
+    })
+}
+
+/// Removes an inline comment starting with ```#``` or ```%``` from a specification value.
+fn strip_comment(val: &str) -> &str {
+    match val.find(['#', '%']) {
+        Some(idx) => val[..idx].trim_end(),
+        None => val,
+    }
+}
+
+/// Checks whether a trimmed line is the keyword ```EOF```, optionally followed by other tokens.
+fn is_eof(line: &str) -> bool {
+    match line.strip_prefix("EOF") {
+        Some(rest) => rest.is_empty() || rest.starts_with(char::is_whitespace),
+        None => false,
+    }
+}
+
+/// A line iterator that keeps a copy of each line it yields.
+struct RecordingLines<'a, I> {
+    inner: &'a mut I,
+    raw: &'a mut Vec<String>,
+}
+
+impl<'a, I> Iterator for RecordingLines<'a, I>
+where
+    I: Iterator,
+    <I as Iterator>::Item: AsRef<str>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.inner.next()?;
+        self.raw.push(String::from(line.as_ref()));
+        Some(line)
     }
 }
 
+/// Checks whether a node is flagged in a vector of flags indexed by node ids.
+#[inline]
+fn is_flagged(flags: &[bool], id: usize) -> bool {
+    flags.get(id).copied().unwrap_or(false)
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 struct InputWrapper<T>(T);
 
@@ -884,6 +3521,29 @@ impl Point {
     }
 }
 
+/// Flags indicating which optional data sections were present in the input.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub struct SectionFlags {
+    /// Whether ```NODE_COORD_SECTION``` was present.
+    pub node_coords: bool,
+    /// Whether ```DEMAND_SECTION``` was present.
+    pub demands: bool,
+    /// Whether ```DEPOT_SECTION``` was present.
+    pub depots: bool,
+    /// Whether ```EDGE_WEIGHT_SECTION``` was present.
+    pub edge_weights: bool,
+    /// Whether ```DISPLAY_DATA_SECTION``` was present.
+    pub display: bool,
+    /// Whether ```FIXED_EDGES_SECTION``` was present.
+    pub fixed_edges: bool,
+    /// Whether ```TOUR_SECTION``` was present.
+    pub tours: bool,
+    /// Whether ```TIME_WINDOW_SECTION``` was present.
+    pub time_windows: bool,
+    /// Whether ```GTSP_SET_SECTION``` was present.
+    pub clusters: bool,
+}
+
 /// Enum for TSP's variants.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub enum TspKind {
@@ -897,6 +3557,9 @@ pub enum TspKind {
     Hcp,
     /// Represents a capacitated vehicle routing problem.
     Cvrp,
+    /// Represents a generalized travelling salesman problem, in which nodes are grouped into
+    /// clusters.
+    Gtsp,
     /// A collection of tours.
     Tour,
     /// The type of problem is not available.
@@ -904,6 +3567,23 @@ pub enum TspKind {
 }
 
 impl_disp_enum!(TspKind);
+impl_from_str_enum!(TspKind);
+
+impl TspKind {
+    /// Returns the string value in TSPLIB format.
+    pub(crate) fn tsp_str(&self) -> &'static str {
+        match self {
+            Self::Tsp => "TSP",
+            Self::Atsp => "ATSP",
+            Self::Sop => "SOP",
+            Self::Hcp => "HCP",
+            Self::Cvrp => "CVRP",
+            Self::Gtsp => "GTSP",
+            Self::Tour => "TOUR",
+            Self::Undefined => "UNDEFINED",
+        }
+    }
+}
 
 impl<T> TryFrom<InputWrapper<T>> for TspKind
 where
@@ -918,6 +3598,7 @@ where
             "SOP" => Ok(Self::Sop),
             "HCP" => Ok(Self::Hcp),
             "CVRP" => Ok(Self::Cvrp),
+            "GTSP" => Ok(Self::Gtsp),
             "TOUR" => Ok(Self::Tour),
             _ => Err(ParseTspError::InvalidInput {
                 key: K_TYPE.to_string(),
@@ -935,6 +3616,7 @@ impl From<&str> for TspKind {
             "SOP" => Self::Sop,
             "HCP" => Self::Hcp,
             "CVRP" => Self::Cvrp,
+            "GTSP" => Self::Gtsp,
             "TOUR" => Self::Tour,
             _ => Self::Undefined,
         }
@@ -975,6 +3657,63 @@ pub enum WeightKind {
 }
 
 impl_disp_enum!(WeightKind);
+impl_from_str_enum!(WeightKind);
+
+impl WeightKind {
+    /// Returns the string value in TSPLIB format.
+    pub(crate) fn tsp_str(&self) -> &'static str {
+        match self {
+            Self::Explicit => "EXPLICIT",
+            Self::Euc2d => "EUC_2D",
+            Self::Euc3d => "EUC_3D",
+            Self::Max2d => "MAX_2D",
+            Self::Max3d => "MAX_3D",
+            Self::Man2d => "MAN_2D",
+            Self::Man3d => "MAN_3D",
+            Self::Ceil2d => "CEIL_2D",
+            Self::Geo => "GEO",
+            Self::Att => "ATT",
+            Self::Xray1 => "XRAY1",
+            Self::Xray2 => "XRAY2",
+            Self::Custom => "SPECIAL",
+            Self::Undefined => "UNDEFINED",
+        }
+    }
+
+    /// Checks whether distances are geographical, i.e. computed on the surface of the Earth.
+    pub fn is_geographic(&self) -> bool {
+        matches!(self, Self::Geo)
+    }
+
+    /// Checks whether distances are Euclidean, i.e. [`WeightKind::Euc2d`], [`WeightKind::Euc3d`]
+    /// or [`WeightKind::Ceil2d`].
+    pub fn is_euclidean(&self) -> bool {
+        matches!(self, Self::Euc2d | Self::Euc3d | Self::Ceil2d)
+    }
+
+    /// Returns a hint for the unit of distances, e.g. for labeling.
+    ///
+    /// - ```"km"``` for [`WeightKind::Geo`].
+    /// - ```"units"``` for distances computed from node coordinates in arbitrary units, i.e.
+    ///   Euclidean, Manhattan, maximum and ```ATT``` distances.
+    /// - ```"unknown"``` otherwise.
+    pub fn unit_hint(&self) -> &'static str {
+        match self {
+            Self::Geo => "km",
+            Self::Euc2d
+            | Self::Euc3d
+            | Self::Max2d
+            | Self::Max3d
+            | Self::Man2d
+            | Self::Man3d
+            | Self::Ceil2d
+            | Self::Att => "units",
+            Self::Explicit | Self::Xray1 | Self::Xray2 | Self::Custom | Self::Undefined => {
+                "unknown"
+            }
+        }
+    }
+}
 
 impl From<&str> for WeightKind {
     fn from(s: &str) -> Self {
@@ -1075,7 +3814,6 @@ pub enum WeightFormat {
 
 impl WeightFormat {
     /// Returns the string value in TSPLIB format.
-    #[allow(dead_code)]
     pub(crate) fn tsp_str(&self) -> &'static str {
         match self {
             WeightFormat::Function => "FUNCTION",
@@ -1091,6 +3829,25 @@ impl WeightFormat {
             WeightFormat::Undefined => "UNDEFINED",
         }
     }
+
+    /// Returns the length of each row of the matrix in which edge weights of this format are
+    /// stored, given the dimension of a problem.
+    ///
+    /// For [`WeightFormat::Function`] and [`WeightFormat::Undefined`], the vector is empty.
+    pub(crate) fn row_lens(&self, dim: usize) -> Vec<usize> {
+        if dim == 0 {
+            return Vec::new();
+        }
+
+        match self {
+            Self::FullMatrix => vec![dim; dim],
+            Self::UpperRow | Self::LowerCol => (1..dim).rev().collect(),
+            Self::LowerRow | Self::UpperCol => (1..dim).collect(),
+            Self::UpperDiagRow | Self::LowerDiagCol => (1..=dim).rev().collect(),
+            Self::LowerDiagRow | Self::UpperDiagCol => (1..=dim).collect(),
+            Self::Function | Self::Undefined => Vec::new(),
+        }
+    }
 }
 
 impl From<&str> for WeightFormat {
@@ -1138,6 +3895,7 @@ where
 }
 
 impl_disp_enum!(WeightFormat);
+impl_from_str_enum!(WeightFormat);
 
 /// Specifies how list of edges are stored in a file.
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -1147,6 +3905,17 @@ pub enum EdgeFormat {
     Undefined,
 }
 
+impl EdgeFormat {
+    /// Returns the string value in TSPLIB format.
+    pub(crate) fn tsp_str(&self) -> &'static str {
+        match self {
+            Self::EdgeList(_) => "EDGE_LIST",
+            Self::AdjList => "ADJ_LIST",
+            Self::Undefined => "UNDEFINED",
+        }
+    }
+}
+
 impl From<&str> for EdgeFormat {
     fn from(s: &str) -> Self {
         match s {
@@ -1190,6 +3959,18 @@ pub enum CoordKind {
     Undefined,
 }
 
+impl CoordKind {
+    /// Returns the string value in TSPLIB format.
+    pub(crate) fn tsp_str(&self) -> &'static str {
+        match self {
+            Self::Coord2d => "TWOD_COORDS",
+            Self::Coord3d => "THREED_COORDS",
+            Self::NoCoord => "NO_COORDS",
+            Self::Undefined => "UNDEFINED",
+        }
+    }
+}
+
 impl From<&str> for CoordKind {
     fn from(s: &str) -> Self {
         match s {
@@ -1236,6 +4017,7 @@ impl From<WeightKind> for CoordKind {
 }
 
 impl_disp_enum!(CoordKind);
+impl_from_str_enum!(CoordKind);
 
 /// Specifies how node coordinates for display purpose are stored in a file.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
@@ -1250,6 +4032,18 @@ pub enum DisplayKind {
     Undefined,
 }
 
+impl DisplayKind {
+    /// Returns the string value in TSPLIB format.
+    pub(crate) fn tsp_str(&self) -> &'static str {
+        match self {
+            Self::DispCoo => "COORD_DISPLAY",
+            Self::Disp2d => "TWOD_DISPLAY",
+            Self::NoDisp => "NO_DISPLAY",
+            Self::Undefined => "UNDEFINED",
+        }
+    }
+}
+
 impl From<&str> for DisplayKind {
     fn from(s: &str) -> Self {
         match s {
@@ -1281,3 +4075,38 @@ where
 }
 
 impl_disp_enum!(DisplayKind);
+impl_from_str_enum!(DisplayKind);
+
+/// Specifies how node ids map to rows and columns of an explicit weight matrix.
+///
+/// TSPLIB numbers nodes from ```1```, so that ids in e.g. ```TOUR_SECTION``` or
+/// ```FIXED_EDGES_SECTION``` correspond to [`MatrixIndexing::OneBased`]. For backward
+/// compatibility, the default is [`MatrixIndexing::ZeroBased`].
+///
+/// The indexing is not part of the TSPLIB format and therefore not written by
+/// [`Tsp::write_tsplib`]. Files written from a dataset with [`MatrixIndexing::OneBased`] must be
+/// read with the same setting to yield the same weights for the same ids.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum MatrixIndexing {
+    /// Node ```i``` refers to row and column ```i``` of the matrix.
+    #[default]
+    ZeroBased,
+    /// Node ```i``` refers to row and column ```i - 1``` of the matrix, as in TSPLIB.
+    OneBased,
+}
+
+/// Specifies how distances of [`WeightKind::Geo`] are rounded to integers.
+///
+/// TSPLIB computes ```(int) (RRR * acos(...) + 1.0)```, i.e. truncates the distance after adding
+/// ```1```. The optimal tour lengths published with TSPLIB, e.g. ```6859``` for ulysses16 or
+/// ```3323``` for burma14, assume [`RoundingPolicy::Truncate`]. Some solvers and converted
+/// datasets round to the nearest integer instead, which corresponds to [`RoundingPolicy::Round`]
+/// and may result in different optima.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash)]
+pub enum RoundingPolicy {
+    /// Distances are truncated, as in TSPLIB.
+    #[default]
+    Truncate,
+    /// Distances are rounded to the nearest integer.
+    Round,
+}
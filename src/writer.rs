@@ -0,0 +1,554 @@
+use std::{collections::HashMap, io::Write};
+
+use crate::{
+    error::ParseTspError,
+    metric,
+    tsp::{
+        K_CAP, K_COMMENT, K_DIM, K_DISP_TYPE, K_EDGE_FORMAT, K_EDGE_WEIGHT_SEC, K_GTSP_SETS,
+        K_GTSP_SET_SEC, K_NAME, K_NODE_COORD_SEC, K_NODE_COORD_TYPE, K_SCALE, K_TIME_WINDOW_SEC,
+        K_TOUR_SEC, K_TYPE, K_VEHICLES, K_WEIGHT_FORMAT, K_WEIGHT_TYPE,
+    },
+    CoordKind, DisplayKind, EdgeFormat, Point, Tsp, TspKind, WeightFormat, WeightKind,
+};
+
+/// Options for writing a [`Tsp`] instance.
+///
+/// # Example
+///
+/// ```
+/// use tspf::{TspBuilder, WriteOptions};
+/// use std::path::Path;
+///
+/// let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+/// let mut out = Vec::new();
+/// tsp.write_tsplib_with(&mut out, &WriteOptions::new().precision(2)).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WriteOptions {
+    precision: Option<usize>,
+    weight_format: Option<WeightFormat>,
+}
+
+impl WriteOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of decimal places for floating-point values.
+    ///
+    /// Values are rounded to the given precision and trailing zeros are removed. Without a
+    /// precision, values are written in their shortest representation that reads back to the
+    /// exact same value.
+    pub fn precision(mut self, precision: usize) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+
+    /// Sets the format in which edge weights are written, e.g. ```UPPER_ROW``` to save space.
+    ///
+    /// The weights are converted with [`Tsp::to_explicit`], so that instances with a distance
+    /// function are written with explicit weights as well. Writing fails if the format is
+    /// triangular but the weights are asymmetric.
+    pub fn weight_format(mut self, format: WeightFormat) -> Self {
+        self.weight_format = Some(format);
+        self
+    }
+}
+
+/// Formats a floating-point value according to the given precision.
+pub(crate) fn fmt_f64(x: f64, precision: Option<usize>) -> String {
+    match precision {
+        Some(p) => {
+            let s = format!("{:.*}", p, x);
+            let s = if s.contains('.') {
+                s.trim_end_matches('0').trim_end_matches('.')
+            } else {
+                s.as_str()
+            };
+
+            match s {
+                "-0" => String::from("0"),
+                _ => String::from(s),
+            }
+        }
+        None => format!("{}", x),
+    }
+}
+
+impl Tsp {
+    /// Writes the dataset in TSPLIB format.
+    ///
+    /// Floating-point values are written in their shortest exact representation, so that reading
+    /// the output back yields the same values. If the dataset was parsed with
+    /// [`TspBuilder::preserve_raw`](crate::TspBuilder::preserve_raw) and not changed since, the
+    /// original input is written verbatim instead. See [`Tsp::write_tsplib_with`] for more
+    /// control.
+    pub fn write_tsplib<W: Write>(&self, w: &mut W) -> Result<(), ParseTspError> {
+        if let Some(raw) = self.raw_lines() {
+            for line in raw {
+                writeln!(w, "{}", line)?;
+            }
+            return Ok(());
+        }
+
+        self.write_tsplib_with(w, &WriteOptions::default())
+    }
+
+    /// Returns the dataset in TSPLIB format, as written by [`Tsp::write_tsplib`].
+    ///
+    /// Parsing the result with [`TspBuilder::parse_str`](crate::TspBuilder::parse_str) yields an
+    /// equivalent dataset.
+    pub fn to_tsplib_string(&self) -> String {
+        let mut buf = Vec::new();
+        // Writing into a vector cannot fail and the output consists of valid UTF-8 only.
+        self.write_tsplib(&mut buf).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    /// Writes the dataset in TSPLIB format with the given options.
+    ///
+    /// The dataset is always serialised from its data, even if the original input was preserved.
+    /// All sections that contain data are written in the format expected by the parser:
+    /// - ```NODE_COORD_SECTION``` with two or three coordinates and the node labels, if any.
+    /// - ```EDGE_DATA_SECTION``` and ```FIXED_EDGES_SECTION```.
+    /// - ```EDGE_WEIGHT_SECTION``` in the weight format of the dataset, one matrix row per line.
+    /// - ```DISPLAY_DATA_SECTION``` for [`DisplayKind::Disp2d`].
+    /// - ```DEMAND_SECTION```, including pickup and delivery amounts, and ```DEPOT_SECTION```.
+    /// - ```TIME_WINDOW_SECTION```, ```GTSP_SET_SECTION``` and ```TOUR_SECTION```.
+    ///
+    /// See [`WriteOptions::weight_format`] for writing edge weights in a different format. The
+    /// [`MatrixIndexing`](crate::MatrixIndexing) of the dataset is not part of the output.
+    pub fn write_tsplib_with<W: Write>(
+        &self,
+        w: &mut W,
+        options: &WriteOptions,
+    ) -> Result<(), ParseTspError> {
+        if let Some(format) = options.weight_format {
+            if format != self.weight_format() || self.weight_kind() != WeightKind::Explicit {
+                if format != WeightFormat::FullMatrix && !self.has_symmetric_weights() {
+                    return Err(ParseTspError::InvalidInput {
+                        key: String::from(K_WEIGHT_FORMAT),
+                        val: String::from(format.tsp_str()),
+                    });
+                }
+                let options = WriteOptions {
+                    weight_format: None,
+                    ..*options
+                };
+                return self.to_explicit(format)?.write_tsplib_with(w, &options);
+            }
+        }
+
+        let fmt = |x: f64| fmt_f64(x, options.precision);
+        self.write_spec(w, options)?;
+
+        if !self.node_coords().is_empty() {
+            writeln!(w, "{}", K_NODE_COORD_SEC)?;
+            for pt in self.sorted_points(self.node_coords().values()) {
+                write!(w, "{} {}", pt.id(), fmt_pos(pt.pos(), options.precision))?;
+                if let Some(label) = self.node_labels().get(&pt.id()) {
+                    write!(w, " {}", label)?;
+                }
+                writeln!(w)?;
+            }
+        }
+
+        if let EdgeFormat::EdgeList(edges) = self.edge_format() {
+            writeln!(w, "EDGE_DATA_SECTION")?;
+            for (a, b) in edges {
+                writeln!(w, "{} {}", a, b)?;
+            }
+            writeln!(w, "-1")?;
+        }
+
+        if !self.fixed_edges().is_empty() {
+            writeln!(w, "FIXED_EDGES_SECTION")?;
+            for (a, b) in self.fixed_edges() {
+                writeln!(w, "{} {}", a, b)?;
+            }
+            writeln!(w, "-1")?;
+        }
+
+        if !self.edge_weights().is_empty() {
+            writeln!(w, "{}", K_EDGE_WEIGHT_SEC)?;
+            for row in self.edge_weights() {
+                let row: Vec<String> = row.iter().map(|&x| fmt(x)).collect();
+                writeln!(w, "{}", row.join(" "))?;
+            }
+        }
+
+        if self.disp_kind() == DisplayKind::Disp2d && !self.disp_coords().is_empty() {
+            writeln!(w, "DISPLAY_DATA_SECTION")?;
+            for pt in self.sorted_points(self.disp_coords().iter()) {
+                writeln!(w, "{} {}", pt.id(), fmt_pos(pt.pos(), options.precision))?;
+            }
+        }
+
+        if !self.demands().is_empty() || !self.pickup_delivery().is_empty() {
+            writeln!(w, "DEMAND_SECTION")?;
+            let mut ids: Vec<usize> = self
+                .demands()
+                .keys()
+                .chain(self.pickup_delivery().keys())
+                .copied()
+                .collect();
+            ids.sort_unstable();
+            ids.dedup();
+            for id in ids {
+                match (self.pickup_delivery().get(&id), self.demands().get(&id)) {
+                    (Some(&(p, d)), _) => writeln!(w, "{} {} {}", id, fmt(p), fmt(d))?,
+                    (None, Some(&d)) => writeln!(w, "{} {}", id, fmt(d))?,
+                    (None, None) => {}
+                }
+            }
+        }
+
+        if !self.depots().is_empty() {
+            writeln!(w, "DEPOT_SECTION")?;
+            for id in self.depots_ordered() {
+                writeln!(w, "{}", id)?;
+            }
+            writeln!(w, "-1")?;
+        }
+
+        if !self.time_windows().is_empty() {
+            writeln!(w, "{}", K_TIME_WINDOW_SEC)?;
+            let mut windows: Vec<_> = self.time_windows().iter().collect();
+            windows.sort_by_key(|(id, _)| **id);
+            for (id, &(e, l)) in windows {
+                writeln!(w, "{} {} {}", id, fmt(e), fmt(l))?;
+            }
+        }
+
+        if !self.clusters().is_empty() {
+            writeln!(w, "{}", K_GTSP_SET_SEC)?;
+            for (i, cluster) in self.clusters().iter().enumerate() {
+                write!(w, "{}", i + 1)?;
+                for id in cluster {
+                    write!(w, " {}", id)?;
+                }
+                writeln!(w, " -1")?;
+            }
+        }
+
+        if !self.tours().is_empty() {
+            writeln!(w, "{}", K_TOUR_SEC)?;
+            for tour in self.tours() {
+                for id in tour {
+                    writeln!(w, "{}", id)?;
+                }
+                writeln!(w, "-1")?;
+            }
+            writeln!(w, "-1")?;
+        }
+
+        writeln!(w, "EOF")?;
+        Ok(())
+    }
+
+    /// Writes the dataset as a problem file for the LKH solver.
+    ///
+    /// Unlike [`Tsp::write_tsplib`], the output is normalised to what LKH accepts:
+    /// - only the canonical keywords are written, without comments.
+    /// - nodes are numbered ```1..=dim``` in the order of their ids.
+    /// - explicit weights are written as ```FULL_MATRIX``` with integer weights, rounded according
+    ///   to [`Tsp::rounded_weight`].
+    /// - capacity and demands of [`TspKind::Cvrp`] are rounded to integers.
+    ///
+    /// Node coordinates are written unchanged. LKH reads them as real numbers and rounds the
+    /// distances computed from them, so rounding the coordinates here would change the instance.
+    ///
+    /// An error is returned if the weights are undefined, i.e. the weight type is
+    /// ```UNDEFINED``` or the weight format is ```FUNCTION```, or if a node has no coordinates.
+    pub fn write_lkh_problem<W: Write>(&self, w: &mut W) -> Result<(), ParseTspError> {
+        let explicit = self.weight_kind() == WeightKind::Explicit;
+        if self.weight_kind() == WeightKind::Undefined
+            || (explicit && self.weight_format() == WeightFormat::Function)
+        {
+            return Err(ParseTspError::InvalidInput {
+                key: String::from(K_WEIGHT_TYPE),
+                val: String::from(self.weight_kind().tsp_str()),
+            });
+        }
+
+        let ids = self.node_ids();
+        let index: HashMap<usize, usize> =
+            ids.iter().enumerate().map(|(i, &id)| (id, i + 1)).collect();
+
+        writeln!(w, "{} : {}", K_NAME, self.name())?;
+        writeln!(w, "{} : {}", K_TYPE, self.kind().tsp_str())?;
+        writeln!(w, "{} : {}", K_DIM, ids.len())?;
+        if self.kind() == TspKind::Cvrp {
+            writeln!(w, "{} : {}", K_CAP, self.capacity().round())?;
+        }
+        writeln!(w, "{} : {}", K_WEIGHT_TYPE, self.weight_kind().tsp_str())?;
+
+        if explicit {
+            writeln!(w, "{} : FULL_MATRIX", K_WEIGHT_FORMAT)?;
+            writeln!(w, "{}", K_EDGE_WEIGHT_SEC)?;
+            for &a in &ids {
+                let row: Vec<String> = ids
+                    .iter()
+                    .map(|&b| format!("{}", self.rounded_weight(a, b)))
+                    .collect();
+                writeln!(w, "{}", row.join(" "))?;
+            }
+        } else {
+            writeln!(w, "{}", K_NODE_COORD_SEC)?;
+            for &id in &ids {
+                let pos = self
+                    .node_coords()
+                    .get(&id)
+                    .ok_or_else(|| ParseTspError::MissingEntry(String::from(K_NODE_COORD_SEC)))?
+                    .pos();
+                writeln!(w, "{} {}", index[&id], fmt_pos(pos, None))?;
+            }
+        }
+
+        if self.kind() == TspKind::Cvrp {
+            writeln!(w, "DEMAND_SECTION")?;
+            for &id in &ids {
+                let demand = self.demands().get(&id).copied().unwrap_or(0.);
+                writeln!(w, "{} {}", index[&id], demand.round())?;
+            }
+
+            writeln!(w, "DEPOT_SECTION")?;
+            for depot in self.depots_ordered() {
+                if let Some(i) = index.get(&depot) {
+                    writeln!(w, "{}", i)?;
+                }
+            }
+            writeln!(w, "-1")?;
+        }
+
+        writeln!(w, "EOF")?;
+        Ok(())
+    }
+
+    /// Writes the node coordinates as plain rows of whitespace-separated values, in the order of
+    /// node ids.
+    ///
+    /// Each row contains the coordinates of one node, without its id.
+    pub fn write_xy<W: Write>(&self, w: &mut W) -> Result<(), ParseTspError> {
+        self.write_xy_with(w, &WriteOptions::default())
+    }
+
+    /// Writes the node coordinates as plain rows with the given options.
+    ///
+    /// See [`Tsp::write_xy`].
+    pub fn write_xy_with<W: Write>(
+        &self,
+        w: &mut W,
+        options: &WriteOptions,
+    ) -> Result<(), ParseTspError> {
+        for pt in self.sorted_points(self.node_coords().values()) {
+            writeln!(w, "{}", fmt_pos(pt.pos(), options.precision))?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the node coordinates in the coordinate format of the DIMACS TSP challenge.
+    ///
+    /// The first line contains the number of nodes, followed by one row ```id x y``` per node in
+    /// the order of node ids. An error is returned if the dataset has no node coordinates.
+    pub fn write_dimacs<W: Write>(&self, w: &mut W) -> Result<(), ParseTspError> {
+        if self.node_coords().is_empty() {
+            return Err(ParseTspError::MissingEntry(String::from(K_NODE_COORD_SEC)));
+        }
+
+        writeln!(w, "{}", self.dim())?;
+        for pt in self.sorted_points(self.node_coords().values()) {
+            writeln!(w, "{} {}", pt.id(), fmt_pos(pt.pos(), None))?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the node coordinates of a dataset with geographical distances as a GeoJSON
+    /// ```FeatureCollection```.
+    ///
+    /// Each node becomes a ```Point``` feature with coordinates ```[lon, lat]``` in decimal
+    /// degrees and its id as property ```id```. An error is returned if the edge weight type is
+    /// not ```GEO```.
+    pub fn write_geojson<W: Write>(&self, w: &mut W) -> Result<(), ParseTspError> {
+        if self.weight_kind() != WeightKind::Geo {
+            return Err(ParseTspError::InvalidInput {
+                key: String::from(K_WEIGHT_TYPE),
+                val: String::from(self.weight_kind().tsp_str()),
+            });
+        }
+
+        write!(w, "{{\"type\":\"FeatureCollection\",\"features\":[")?;
+        for (i, pt) in self
+            .sorted_points(self.node_coords().values())
+            .into_iter()
+            .enumerate()
+        {
+            let (lat, lon) = if self.geo_lon_lat() {
+                (pt.pos()[1], pt.pos()[0])
+            } else {
+                (pt.pos()[0], pt.pos()[1])
+            };
+            if i > 0 {
+                write!(w, ",")?;
+            }
+            write!(
+                w,
+                "{{\"type\":\"Feature\",\"geometry\":{{\"type\":\"Point\",\"coordinates\":[{},{}]}},\"properties\":{{\"id\":{}}}}}",
+                fmt_f64(metric::geo_degrees(lon), None),
+                fmt_f64(metric::geo_degrees(lat), None),
+                pt.id()
+            )?;
+        }
+        writeln!(w, "]}}")?;
+
+        Ok(())
+    }
+
+    /// Writes the node coordinates as newline-delimited JSON, in the order of node ids.
+    ///
+    /// Each line contains one object ```{"id":1,"x":38.24,"y":20.42}```, with an additional
+    /// field ```z``` for three-dimensional coordinates. This allows large instances to be
+    /// streamed and parsed incrementally.
+    pub fn write_coords_ndjson<W: Write>(&self, w: &mut W) -> Result<(), ParseTspError> {
+        for pt in self.sorted_points(self.node_coords().values()) {
+            write!(w, "{{\"id\":{}", pt.id())?;
+            for (name, x) in ["x", "y", "z"].iter().zip(pt.pos()) {
+                write!(w, ",\"{}\":{}", name, fmt_f64(*x, None))?;
+            }
+            writeln!(w, "}}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the graph of a sparse dataset in the DOT language of Graphviz.
+    ///
+    /// Each node becomes a vertex, with attribute ```pos``` if it has 2D coordinates, and each
+    /// edge of ```EDGE_DATA_SECTION``` becomes an undirected edge. An error is returned for
+    /// complete graphs, i.e. if the edge data format is not ```EDGE_LIST```, since their
+    /// ```O(n²)``` edges cannot be drawn in a useful way.
+    pub fn write_dot<W: Write>(&self, w: &mut W) -> Result<(), ParseTspError> {
+        let edges = match self.edge_format() {
+            EdgeFormat::EdgeList(edges) => edges,
+            other => {
+                return Err(ParseTspError::InvalidInput {
+                    key: String::from(K_EDGE_FORMAT),
+                    val: String::from(other.tsp_str()),
+                })
+            }
+        };
+
+        writeln!(w, "graph \"{}\" {{", self.name().replace('"', "\\\""))?;
+        if self.node_coords().is_empty() {
+            for id in 1..=self.dim() {
+                writeln!(w, "  {};", id)?;
+            }
+        } else {
+            for pt in self.sorted_points(self.node_coords().values()) {
+                match pt.pos().as_slice() {
+                    [x, y] => writeln!(
+                        w,
+                        "  {} [pos=\"{},{}!\"];",
+                        pt.id(),
+                        fmt_f64(*x, None),
+                        fmt_f64(*y, None)
+                    )?,
+                    _ => writeln!(w, "  {};", pt.id())?,
+                }
+            }
+        }
+        for (a, b) in edges {
+            writeln!(w, "  {} -- {};", a, b)?;
+        }
+        writeln!(w, "}}")?;
+
+        Ok(())
+    }
+
+    /// Writes the specification part.
+    ///
+    /// Keywords are written in the order of [`Tsp::spec_key_order`] if available, followed by any
+    /// other keywords in the default order.
+    fn write_spec<W: Write>(&self, w: &mut W, options: &WriteOptions) -> Result<(), ParseTspError> {
+        let mut entries: Vec<(&str, String)> = Vec::new();
+
+        entries.push((K_NAME, self.name().clone()));
+        entries.push((K_TYPE, self.kind().tsp_str().to_string()));
+        if !self.comment().is_empty() {
+            entries.push((K_COMMENT, self.comment().clone()));
+        }
+        if self.kind() != TspKind::Tour || self.dim() > 0 {
+            entries.push((K_DIM, self.dim().to_string()));
+        }
+        if self.kind() == TspKind::Cvrp {
+            entries.push((K_CAP, fmt_f64(self.capacity(), options.precision)));
+        }
+        if let Some(scale) = self.scale_factor() {
+            entries.push((K_SCALE, fmt_f64(scale, options.precision)));
+        }
+        if let Some(n) = self.vehicles() {
+            entries.push((K_VEHICLES, n.to_string()));
+        }
+        if !self.clusters().is_empty() {
+            entries.push((K_GTSP_SETS, self.clusters().len().to_string()));
+        }
+        if self.weight_kind() != WeightKind::Undefined {
+            entries.push((K_WEIGHT_TYPE, self.weight_kind().tsp_str().to_string()));
+        }
+        if self.weight_format() != WeightFormat::Undefined {
+            entries.push((K_WEIGHT_FORMAT, self.weight_format().tsp_str().to_string()));
+        }
+        if self.edge_format() != &EdgeFormat::Undefined {
+            entries.push((K_EDGE_FORMAT, self.edge_format().tsp_str().to_string()));
+        }
+        // The coordinate type is derived from the weight type during parsing, hence it is only
+        // written if it differs.
+        if self.coord_kind() != CoordKind::Undefined
+            && self.coord_kind() != CoordKind::from(self.weight_kind())
+        {
+            entries.push((K_NODE_COORD_TYPE, self.coord_kind().tsp_str().to_string()));
+        }
+        if self.disp_kind() != DisplayKind::Undefined {
+            entries.push((K_DISP_TYPE, self.disp_kind().tsp_str().to_string()));
+        }
+
+        let order = self.spec_key_order();
+        entries.sort_by_key(|(key, _)| order.iter().position(|k| k == key).unwrap_or(order.len()));
+
+        for (key, val) in entries {
+            writeln!(w, "{}: {}", key, val)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether ```weight(a, b)``` equals ```weight(b, a)``` for all pairs of nodes.
+    fn has_symmetric_weights(&self) -> bool {
+        let ids = self.node_ids();
+        ids.iter().enumerate().all(|(i, &a)| {
+            ids.iter()
+                .skip(i + 1)
+                .all(|&b| self.weight(a, b) == self.weight(b, a))
+        })
+    }
+
+    /// Returns the given points sorted by their ids.
+    fn sorted_points<'a, I>(&self, points: I) -> Vec<&'a Point>
+    where
+        I: Iterator<Item = &'a Point>,
+    {
+        let mut v: Vec<&Point> = points.collect();
+        v.sort_by_key(|pt| pt.id());
+        v
+    }
+}
+
+/// Formats the coordinates of a point.
+fn fmt_pos(pos: &[f64], precision: Option<usize>) -> String {
+    pos.iter()
+        .map(|x| fmt_f64(*x, precision))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
@@ -0,0 +1,7 @@
+NAME: split4
+TYPE: TSP
+COMMENT: Specification part of a dataset split into two files
+DIMENSION: 4
+EDGE_WEIGHT_TYPE: EXPLICIT
+EDGE_WEIGHT_FORMAT: UPPER_ROW
+EOF
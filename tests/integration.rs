@@ -1,6 +1,10 @@
-use std::path::Path;
+use std::{
+    collections::HashSet,
+    io::{BufReader, Read},
+    path::Path,
+};
 
-use tspf::{TspBuilder, TspKind, WeightKind};
+use tspf::{metric, ParseTspError, Point, TspBuilder, TspKind, WeightFormat, WeightKind};
 
 #[test]
 fn parse_vrp() {
@@ -26,3 +30,521 @@ fn parse_tsp() {
     let pt = tsp.node_coords().get(&52).unwrap();
     assert_eq!(&vec![1740_f64, 245_f64], pt.pos());
 }
+
+#[test]
+fn load_tours() {
+    let mut tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    tsp.load_tour(Path::new("./tests/data/berlin52.opt.tour"))
+        .unwrap();
+    tsp.load_tour(Path::new("./tests/data/berlin52.seq.tour"))
+        .unwrap();
+    assert_eq!(2, tsp.tours().len());
+
+    let lengths = tsp.tour_lengths();
+    assert_eq!(2, lengths.len());
+    // The optimal length 7542 is obtained with edge weights rounded to the nearest integer.
+    assert!((lengths[0] - 7544.366).abs() < 1e-3, "{}", lengths[0]);
+    assert!(lengths[0] < lengths[1]);
+
+    let mut tsp = TspBuilder::parse_path(Path::new("./tests/data/eil22.vrp")).unwrap();
+    assert!(tsp
+        .load_tour(Path::new("./tests/data/berlin52.opt.tour"))
+        .is_err());
+    assert!(tsp.tours().is_empty());
+}
+
+#[test]
+fn write_dimacs() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let mut out = Vec::new();
+    tsp.write_dimacs(&mut out).unwrap();
+    let s = String::from_utf8(out).unwrap();
+    let mut lines = s.lines();
+    assert_eq!(
+        tsp.dim(),
+        lines.next().unwrap().trim().parse::<usize>().unwrap()
+    );
+    assert_eq!(Some("1 565 575"), lines.next());
+    assert_eq!(tsp.dim(), lines.count() + 1);
+}
+
+#[test]
+fn neighbors() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let mut excluded = HashSet::new();
+    assert_eq!(Some(52), tsp.furthest_neighbor(2, &excluded));
+    assert_eq!(Some(7), tsp.nearest_neighbor(2, &excluded));
+
+    excluded.insert(52);
+    excluded.insert(7);
+    assert_eq!(Some(11), tsp.furthest_neighbor(2, &excluded));
+    assert_eq!(Some(42), tsp.nearest_neighbor(2, &excluded));
+
+    let excluded = (1..=52).collect();
+    assert_eq!(None, tsp.furthest_neighbor(2, &excluded));
+}
+
+#[test]
+fn nearest_neighbor_bitset() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let mut visited = tsp.visited_bitset();
+    assert_eq!(tsp.dim() + 1, visited.len());
+
+    let mut tour = vec![1];
+    visited[1] = true;
+    while let Some(next) = tsp.nearest_unvisited(*tour.last().unwrap(), &visited) {
+        visited[next] = true;
+        tour.push(next);
+    }
+
+    assert_eq!(tsp.dim(), tour.len());
+    assert!(visited.iter().skip(1).all(|v| *v));
+    assert_eq!(Some(52), tsp.furthest_unvisited(2, &tsp.visited_bitset()));
+}
+
+#[test]
+fn fleet_capacity() {
+    let s = std::fs::read_to_string("./tests/data/eil22.vrp").unwrap();
+    let tsp = TspBuilder::parse_str(&s).unwrap();
+    assert_eq!(None, tsp.vehicles());
+    assert_eq!(None, tsp.fleet_capacity());
+    assert_eq!(None, tsp.is_feasible_fleet());
+    assert_eq!(22500., tsp.total_demand());
+
+    let tsp = TspBuilder::parse_str(s.replace("CAPACITY : 6000", "CAPACITY : 6000\nVEHICLES : 4"))
+        .unwrap();
+    assert_eq!(Some(4), tsp.vehicles());
+    assert_eq!(Some(24000.), tsp.fleet_capacity());
+    assert_eq!(Some(true), tsp.is_feasible_fleet());
+    let other = TspBuilder::parse_str(tsp.to_tsplib_string()).unwrap();
+    assert_eq!(Some(4), other.vehicles());
+
+    let tsp = TspBuilder::parse_str(s.replace("CAPACITY : 6000", "CAPACITY : 6000\nVEHICLES : 3"))
+        .unwrap();
+    assert_eq!(Some(false), tsp.is_feasible_fleet());
+}
+
+#[test]
+fn to_tsplib_string() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/eil22.vrp")).unwrap();
+    let other = TspBuilder::parse_str(tsp.to_tsplib_string()).unwrap();
+    assert_eq!(tsp.capacity(), other.capacity());
+    assert_eq!(tsp.demands(), other.demands());
+    assert_eq!(tsp.depots(), other.depots());
+    assert_eq!(tsp.node_labels(), other.node_labels());
+
+    let mut tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    tsp.load_tour(Path::new("./tests/data/berlin52.opt.tour"))
+        .unwrap();
+    let other = TspBuilder::parse_str(tsp.to_tsplib_string()).unwrap();
+    assert_eq!(tsp.tours(), other.tours());
+    assert_eq!(tsp.tour_lengths(), other.tour_lengths());
+}
+
+#[test]
+fn preserve_raw() {
+    let path = Path::new("./tests/data/berlin52.tsp");
+    let tsp = TspBuilder::new()
+        .preserve_raw(true)
+        .read_path(path)
+        .unwrap();
+    assert_eq!(52, tsp.dim());
+
+    let mut out = Vec::new();
+    tsp.write_tsplib(&mut out).unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), out);
+
+    let mut tsp = tsp;
+    tsp.node_coords_mut().insert(1, Point::new2(1, 1.5, 2.5));
+    let other = TspBuilder::parse_str(tsp.to_tsplib_string()).unwrap();
+    assert_eq!(&vec![1.5, 2.5], other.node_coords().get(&1).unwrap().pos());
+}
+
+#[test]
+fn weight_matrix_u32() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let m = tsp.weight_matrix_u32();
+    assert_eq!(52, m.len());
+    assert!(m.iter().all(|row| row.len() == 52));
+    assert_eq!(0, m[0][0]);
+    assert_eq!(tsp.weight(1, 2).round() as u32, m[0][1]);
+    assert_eq!(tsp.weight(52, 14).round() as u32, m[51][13]);
+    assert_eq!(m[13][51], m[51][13]);
+
+    // The optimal tour has length 7542 with rounded weights.
+    let tsp = {
+        let mut tsp = tsp;
+        tsp.load_tour(Path::new("./tests/data/berlin52.opt.tour"))
+            .unwrap();
+        tsp
+    };
+    let len: u32 = tsp
+        .tour_edges(&tsp.tours()[0])
+        .map(|(a, b)| m[a - 1][b - 1])
+        .sum();
+    assert_eq!(7542, len);
+}
+
+#[test]
+fn weight_matrix_scaled_i64() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let m = tsp.weight_matrix_scaled_i64(1000.);
+    assert_eq!(52, m.len());
+    assert_eq!(0, m[0][0]);
+    // Nodes 1 and 2 are at (565, 575) and (25, 185).
+    assert_eq!(666_108, m[0][1]);
+    assert_eq!((tsp.weight(52, 14) * 1000.).round() as i64, m[51][13]);
+}
+
+#[test]
+fn coords_soa() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let (ids, xs, ys) = tsp.coords_soa();
+    assert_eq!(tsp.dim(), ids.len());
+    assert_eq!(tsp.dim(), xs.len());
+    assert_eq!(tsp.dim(), ys.len());
+    assert!(ids.windows(2).all(|w| w[0] < w[1]));
+    assert_eq!((565., 575.), (xs[0], ys[0]));
+}
+
+#[test]
+fn rounding_report() {
+    let mut tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    tsp.load_tour(Path::new("./tests/data/berlin52.opt.tour"))
+        .unwrap();
+    let tour = tsp.tours()[0].clone();
+
+    let (exact, rounded) = tsp.rounding_report(&tour);
+    assert_eq!(tsp.tour_length(&tour), exact);
+    assert_eq!(7542., rounded);
+    assert!((exact - rounded).abs() > 1.);
+}
+
+#[test]
+fn present_sections() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/eil22.vrp")).unwrap();
+    let flags = tsp.present_sections();
+    assert!(flags.node_coords);
+    assert!(flags.demands);
+    assert!(flags.depots);
+    assert!(!flags.edge_weights);
+    assert!(!flags.tours);
+
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let flags = tsp.present_sections();
+    assert!(flags.node_coords);
+    assert!(!flags.demands);
+    assert!(!flags.depots);
+}
+
+#[test]
+fn parse_tab_separated_matrix() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/tabs5.atsp")).unwrap();
+    assert_eq!(TspKind::Atsp, tsp.kind());
+    assert_eq!(5, tsp.edge_weights().len());
+    assert!(tsp.edge_weights().iter().all(|row| row.len() == 5));
+    assert_eq!(&vec![0., 12., 3., 1004., 7.], &tsp.edge_weights()[0]);
+    assert_eq!(&vec![8., 0., 15., 2., 9.], &tsp.edge_weights()[1]);
+    assert_eq!(&vec![17., 4., 6., 30., 0.], &tsp.edge_weights()[4]);
+    assert_eq!(40., tsp.weight(2, 3));
+    assert_eq!(21., tsp.weight(3, 2));
+}
+
+#[test]
+fn nearest_neighbor_tour() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let tour = tsp.nearest_neighbor_tour(1);
+    assert_eq!(52, tour.len());
+    assert_eq!(1, tour[0]);
+    let nodes: HashSet<usize> = tour.iter().copied().collect();
+    assert_eq!((1..=52).collect::<HashSet<usize>>(), nodes);
+
+    // The optimal tour has a length of 7542. Nearest neighbor tours are usually within 25%.
+    let len = tsp.tour_length(&tour);
+    assert!((7542. ..=1.25 * 7542.).contains(&len), "{}", len);
+}
+
+#[test]
+fn nearest_node_to() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    // Node 2 is at (25, 185).
+    assert_eq!(Some(2), tsp.nearest_node_to(&[27., 183.]));
+    assert_eq!(8_f64.sqrt(), tsp.weight_to_coord(2, &[27., 183.]));
+    for id in 1..=52 {
+        let pos = tsp.node_coords().get(&id).unwrap().pos().clone();
+        assert_eq!(Some(id), tsp.nearest_node_to(&pos));
+    }
+
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/tabs5.atsp")).unwrap();
+    assert_eq!(None, tsp.nearest_node_to(&[0., 0.]));
+}
+
+#[test]
+fn nodes_in_box() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let (min, max) = ([0., 0.], [880., 587.5]);
+    let inside: Vec<usize> = (1..=52)
+        .filter(|id| {
+            let pos = tsp.node_coords().get(id).unwrap().pos();
+            pos[0] <= max[0] && pos[1] <= max[1]
+        })
+        .collect();
+
+    let sub = tsp.nodes_in_box(&min, &max).unwrap();
+    assert!(!inside.is_empty() && inside.len() < 52);
+    assert_eq!(inside.len(), sub.dim());
+    assert_eq!(inside.len(), sub.node_coords().len());
+    for (idx, &id) in inside.iter().enumerate() {
+        assert_eq!(
+            tsp.node_coords().get(&id).unwrap().pos(),
+            sub.node_coords().get(&(idx + 1)).unwrap().pos()
+        );
+    }
+    assert_eq!(tsp.weight(inside[0], inside[1]), sub.weight(1, 2));
+    assert!(sub.check_consistency().is_empty());
+
+    assert!(tsp.nodes_in_box(&[0.], &[1.]).is_err());
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/tabs5.atsp")).unwrap();
+    assert!(tsp.nodes_in_box(&min, &max).is_err());
+}
+
+#[cfg(feature = "petgraph")]
+#[test]
+fn to_petgraph() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let graph = tsp.to_petgraph();
+    assert_eq!(52, graph.node_count());
+    assert_eq!(52 * 51 / 2, graph.edge_count());
+    let total: f64 = graph.edge_weights().sum();
+    let expected: f64 = tsp.edge_list().iter().map(|e| e.2).sum();
+    assert_eq!(expected, total);
+}
+
+#[test]
+fn sorted_neighbors() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let v = tsp.sorted_neighbors(2);
+    assert_eq!(51, v.len());
+    assert!(v.iter().all(|&(id, _)| id != 2));
+    assert!(v.windows(2).all(|w| w[0].1 <= w[1].1));
+    assert_eq!(7, v[0].0);
+    assert_eq!(52, v[50].0);
+}
+
+#[test]
+fn route_length() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/eil22.vrp")).unwrap();
+    let len = tsp.route_length(&[2, 3, 4], 1);
+    let expected = tsp.weight(1, 2) + tsp.weight(2, 3) + tsp.weight(3, 4) + tsp.weight(4, 1);
+    assert!((len - expected).abs() < 1e-9);
+    assert!((tsp.route_length(&[2, 3, 4], 1) - tsp.tour_length(&[1, 2, 3, 4])).abs() < 1e-9);
+    assert_eq!(0., tsp.route_length(&[], 1));
+}
+
+#[test]
+fn validate_routes() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/eil22.vrp")).unwrap();
+
+    // Split the customers into routes in order of their ids without exceeding the capacity.
+    let mut routes: Vec<Vec<usize>> = vec![Vec::new()];
+    let mut load = 0.;
+    for id in 2..=22 {
+        let demand = tsp.demands()[&id];
+        if load + demand > tsp.capacity() {
+            routes.push(Vec::new());
+            load = 0.;
+        }
+        routes.last_mut().unwrap().push(id);
+        load += demand;
+    }
+    assert!(tsp.validate_routes(&routes).is_ok());
+
+    let with_depots: Vec<Vec<usize>> = routes
+        .iter()
+        .map(|r| [&[1], r.as_slice(), &[1]].concat())
+        .collect();
+    assert!(tsp.validate_routes(&with_depots).is_ok());
+
+    let over_capacity = vec![(2..=22).collect::<Vec<usize>>()];
+    let err = tsp.validate_routes(&over_capacity).unwrap_err();
+    assert!(err.contains("exceeds capacity"), "{}", err);
+
+    let mut missing = routes.clone();
+    missing[0].pop();
+    assert!(tsp.validate_routes(&missing).is_err());
+}
+
+#[test]
+fn atsp_full_matrix_asymmetry() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/tabs5.atsp")).unwrap();
+    assert_eq!(1004., tsp.weight(0, 3));
+    assert_eq!(5., tsp.weight(3, 0));
+    assert_eq!(vec![0., 12., 3., 1004., 7.], tsp.weight_row(0));
+
+    let m = tsp.weight_matrix_u32();
+    assert_eq!(1004, m[0][3]);
+    assert_eq!(5, m[3][0]);
+
+    let forward = tsp.tour_length(&[0, 1, 2, 3, 4]);
+    let backward = tsp.tour_length(&[4, 3, 2, 1, 0]);
+    assert_eq!(12. + 15. + 40. + 3. + 17., forward);
+    assert_eq!(30. + 21. + 6. + 8. + 7., backward);
+}
+
+#[test]
+fn parse_path_with_data() {
+    let tsp = TspBuilder::parse_path_with_data(
+        Path::new("./tests/data/split4.spec"),
+        Path::new("./tests/data/split4.data"),
+    )
+    .unwrap();
+    assert_eq!("split4", tsp.name());
+    assert_eq!(4, tsp.dim());
+    assert_eq!(WeightKind::Explicit, tsp.weight_kind());
+    assert_eq!(10., tsp.weight(0, 1));
+    assert_eq!(50., tsp.weight(3, 1));
+    assert_eq!(60., tsp.weight(2, 3));
+
+    assert!(TspBuilder::parse_path(Path::new("./tests/data/split4.spec")).is_err());
+}
+
+#[test]
+fn empty_name_defaults_to_file_stem() {
+    let (tsp, warnings) = TspBuilder::new()
+        .load_path(Path::new("./tests/data/unnamed3.tsp"))
+        .and_then(|b| b.build_with_warnings())
+        .unwrap();
+    assert_eq!("unnamed3", tsp.name());
+    assert_eq!(1, warnings.len());
+
+    let s = std::fs::read_to_string("./tests/data/unnamed3.tsp").unwrap();
+    assert_eq!("", TspBuilder::parse_str(s).unwrap().name());
+}
+
+#[test]
+fn euc_2d_fast_path() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+    let pts: Vec<&Vec<f64>> = (1..=52)
+        .map(|id| tsp.node_coords().get(&id).unwrap().pos())
+        .collect();
+    // Generic computation over the coordinates, as used for all Euclidean distances before.
+    let generic = |a: &[f64], b: &[f64]| -> f64 {
+        a.iter()
+            .zip(b.iter())
+            .fold(0_f64, |acc, (x1, x2)| acc + (x1 - x2).powi(2))
+            .sqrt()
+    };
+
+    for a in 1..=52 {
+        for b in 1..=52 {
+            let expected = generic(pts[a - 1], pts[b - 1]);
+            assert_eq!(expected, metric::euc_2d(pts[a - 1], pts[b - 1]));
+            assert_eq!(expected, tsp.weight(a, b));
+        }
+    }
+}
+
+#[test]
+fn to_explicit() {
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/berlin52.tsp")).unwrap();
+
+    for format in [
+        WeightFormat::FullMatrix,
+        WeightFormat::UpperRow,
+        WeightFormat::LowerDiagRow,
+        WeightFormat::UpperCol,
+    ] {
+        let explicit = tsp.to_explicit(format).unwrap();
+        assert_eq!(WeightKind::Explicit, explicit.weight_kind());
+        assert_eq!(format, explicit.weight_format());
+        assert_eq!(52, explicit.node_coords().len());
+        assert!(explicit.check_consistency().is_empty());
+        let reparsed = TspBuilder::new()
+            .matrix_indexing(explicit.matrix_indexing())
+            .read_str(explicit.to_tsplib_string())
+            .unwrap();
+        for a in 1..=52 {
+            for b in 1..=52 {
+                assert_eq!(tsp.weight(a, b), explicit.weight(a, b), "{} {}", a, b);
+                assert_eq!(tsp.weight(a, b), reparsed.weight(a, b), "{} {}", a, b);
+            }
+        }
+    }
+
+    assert!(tsp.to_explicit(WeightFormat::Function).is_err());
+
+    let tsp = TspBuilder::parse_path(Path::new("./tests/data/tabs5.atsp")).unwrap();
+    let explicit = tsp.to_explicit(WeightFormat::FullMatrix).unwrap();
+    assert_eq!(tsp.edge_weights(), explicit.edge_weights());
+}
+
+/// Generates the lines of a large instance on the fly, so that the input is never held in memory
+/// as a whole.
+struct SyntheticInstance {
+    dim: usize,
+    line: usize,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl Read for SyntheticInstance {
+    fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+        if self.pos == self.buf.len() {
+            self.buf.clear();
+            self.pos = 0;
+            let header = [
+                String::from("NAME: synthetic"),
+                String::from("TYPE: TSP"),
+                format!("DIMENSION: {}", self.dim),
+                String::from("EDGE_WEIGHT_TYPE: EUC_2D"),
+                String::from("NODE_COORD_SECTION"),
+            ];
+            let line = match self.line {
+                i if i < header.len() => header[i].clone(),
+                i if i < header.len() + self.dim => {
+                    let id = i - header.len() + 1;
+                    format!("{} {} {}", id, id % 1000, id / 1000)
+                }
+                i if i == header.len() + self.dim => String::from("EOF"),
+                _ => return Ok(0),
+            };
+            self.line += 1;
+            self.buf.extend_from_slice(line.as_bytes());
+            self.buf.push(b'\n');
+        }
+
+        let n = out.len().min(self.buf.len() - self.pos);
+        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn parse_reader_large_instance() {
+    let dim = 1_000_000;
+    let reader = BufReader::new(SyntheticInstance {
+        dim,
+        line: 0,
+        buf: Vec::new(),
+        pos: 0,
+    });
+    let tsp = TspBuilder::parse_reader(reader).unwrap();
+    assert_eq!(dim, tsp.dim());
+    assert_eq!(dim, tsp.node_coords().len());
+    assert_eq!(
+        &vec![999., 999.],
+        tsp.node_coords().get(&999_999).unwrap().pos()
+    );
+    assert_eq!(1., tsp.weight(1, 1001));
+}
+
+#[test]
+fn parse_reader_invalid_utf8() {
+    let input: &[u8] = b"NAME: test\nTYPE: TSP\nCOMMENT: \xff\xfe\nDIMENSION: 3\nEOF\n";
+    let result = TspBuilder::parse_reader(input);
+    assert!(matches!(result, Err(ParseTspError::IoError(_))));
+
+    let result = TspBuilder::new().preserve_raw(true).read_reader(input);
+    assert!(matches!(result, Err(ParseTspError::IoError(_))));
+}